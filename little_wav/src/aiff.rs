@@ -0,0 +1,407 @@
+use crate::{
+    chunk::{push_chunk, Chunk, RawChunk},
+    chunk_reader::ChunkReader,
+    codable::read_exact,
+    container::Container,
+    error::Error,
+    four_cc::FourCC,
+    header_format::{Encoding, HeaderFormat},
+    sample::{swap_bytes, ByteOrder, Sample},
+    wav::Wav,
+};
+use std::io::Read;
+
+// Apple's AIFF is an IFF container: big-endian sizes and samples, signed 8-bit samples,
+// and the sample rate as an 80-bit extended float. AIFF-C adds a compression type.
+
+pub(crate) const FORM_ID: &[u8; 4] = b"FORM";
+const AIFF_TYPE: &[u8; 4] = b"AIFF";
+const AIFC_TYPE: &[u8; 4] = b"AIFC";
+const COMM_TYPE: &[u8; 4] = b"COMM";
+const SSND_TYPE: &[u8; 4] = b"SSND";
+const FVER_TYPE: &[u8; 4] = b"FVER";
+
+// The one AIFF-C version there is, as FVER holds it
+const AIFC_VERSION: u32 = 0xa280_5140;
+
+// Compression type with its display name, in Mac Roman, for the encodings we can write
+fn compression(
+    encoding: Encoding,
+    sample_size: usize,
+    byte_order: ByteOrder,
+) -> (&'static [u8; 4], &'static [u8]) {
+    match (encoding, sample_size, byte_order) {
+        (Encoding::LPCM, _, ByteOrder::BigEndian) => (b"NONE", b"not compressed"),
+        (Encoding::LPCM, _, ByteOrder::LittleEndian) => (b"sowt", b""),
+        (Encoding::IEEE, 8, _) => (b"fl64", b"64-bit floating point"),
+        (Encoding::IEEE, _, _) => (b"fl32", b"32-bit floating point"),
+        (Encoding::ALAW, _, _) => (b"alaw", b"ALaw 2:1"),
+        (Encoding::MULAW, _, _) => (b"ulaw", b"\xb5Law 2:1"),
+    }
+}
+
+pub(crate) fn decode<R: Read, T: Sample>(mut reader: R) -> Result<Wav<T>, Error> {
+    let mut buffer_16 = [0; 4];
+
+    read_exact(&mut reader, &mut buffer_16, FORM_ID)?;
+    if buffer_16 != *FORM_ID {
+        return Err(Error::BadMagic {
+            expected: FourCC::new(*FORM_ID),
+            found: FourCC::new(buffer_16),
+        });
+    }
+    read_exact(&mut reader, &mut buffer_16, FORM_ID)?;
+    let form_size = u32::from_be_bytes(buffer_16) as u64;
+    read_exact(&mut reader, &mut buffer_16, FORM_ID)?;
+    let is_aifc = match &buffer_16 {
+        AIFF_TYPE => false,
+        AIFC_TYPE => true,
+        _ => {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*AIFF_TYPE),
+                found: FourCC::new(buffer_16),
+            })
+        }
+    };
+
+    let mut comm = None;
+    let mut ssnd = None;
+    let mut chunks = Vec::new();
+
+    let mut walker =
+        ChunkReader::new(reader, form_size.saturating_sub(4)).with_byte_order(ByteOrder::BigEndian);
+    while let Some(header) = walker.next_header()? {
+        match header.get_be_id() {
+            COMM_TYPE if comm.is_none() => comm = Some(walker.read_body()?),
+            SSND_TYPE if ssnd.is_none() => ssnd = Some(walker.read_partial_body()?),
+            // Rewritten on encode
+            FVER_TYPE => {}
+            _ => chunks.push(RawChunk::new(header.id(), walker.read_body()?)),
+        }
+    }
+
+    let comm = comm.ok_or(Error::MissingChunk(FourCC::new(*COMM_TYPE)))?;
+    let ssnd = ssnd.ok_or(Error::MissingChunk(FourCC::new(*SSND_TYPE)))?;
+
+    let min_size = if is_aifc { 22 } else { 18 };
+    if comm.len() < min_size {
+        return Err(Error::TruncatedChunk(FourCC::new(*COMM_TYPE)));
+    }
+    let channels = u16::from_be_bytes([comm[0], comm[1]]);
+    let bits_per_sample = u16::from_be_bytes([comm[6], comm[7]]);
+    let sample_rate = from_extended(comm[8..18].try_into().expect("checked above"));
+    let compression = if is_aifc {
+        FourCC::new([comm[18], comm[19], comm[20], comm[21]])
+    } else {
+        FourCC::new(*b"NONE")
+    };
+
+    let (encoding, byte_order) = match compression.as_array() {
+        b"NONE" | b"twos" => (Encoding::LPCM, ByteOrder::BigEndian),
+        b"sowt" => (Encoding::LPCM, ByteOrder::LittleEndian),
+        b"fl32" | b"FL32" | b"fl64" | b"FL64" => (Encoding::IEEE, ByteOrder::BigEndian),
+        b"alaw" | b"ALAW" => (Encoding::ALAW, ByteOrder::BigEndian),
+        b"ulaw" | b"ULAW" => (Encoding::MULAW, ByteOrder::BigEndian),
+        _ => return Err(Error::UnsupportedCompression(compression)),
+    };
+    // Companded samples are a byte each, whatever COMM says the expanded size is
+    let sample_size = match (encoding, compression.as_array()) {
+        (Encoding::ALAW | Encoding::MULAW, _) => 1,
+        (_, b"fl32" | b"FL32") => 4,
+        (_, b"fl64" | b"FL64") => 8,
+        _ => (bits_per_sample as usize).div_ceil(8),
+    };
+
+    let mut format = HeaderFormat::new(
+        encoding,
+        channels as usize,
+        sample_rate.round() as usize,
+        sample_size,
+    );
+    // e.g. 20 bits samples sit in 3 bytes, left-justified like WAV does
+    if encoding == Encoding::LPCM && (bits_per_sample as usize) < sample_size * 8 {
+        format.valid_bits_per_sample(bits_per_sample);
+    }
+    // Before the body gets swapped by sample size
+    format.check()?;
+
+    // Sound data starts `offset` bytes in, after the offset and block size fields
+    let offset = match ssnd.get(..4) {
+        Some(offset) => 8 + u32::from_be_bytes(offset.try_into().expect("4 bytes")) as usize,
+        None => return Err(Error::TruncatedChunk(FourCC::new(*SSND_TYPE))),
+    };
+    let mut body = ssnd.get(offset..).unwrap_or_default().to_vec();
+    to_wav_layout(&mut body, encoding, sample_size, byte_order);
+
+    let container = if is_aifc {
+        Container::AiffC(byte_order)
+    } else {
+        Container::Aiff
+    };
+    Wav::from_parts(container, format, None, chunks, &body)
+}
+
+pub(crate) fn encode<T: Sample>(wav: &Wav<T>) -> Result<Vec<u8>, Error> {
+    let format = wav.format();
+    let encoding = format.get_encoding();
+    let sample_size = format.get_sample_size();
+
+    // Plain AIFF only holds big-endian integers, and only integers may be sowt
+    let byte_order = match (wav.container(), encoding) {
+        (Container::AiffC(byte_order), Encoding::LPCM) => byte_order,
+        _ => ByteOrder::BigEndian,
+    };
+    let is_aifc = encoding != Encoding::LPCM || wav.container() != Container::Aiff;
+
+    let mut vec = Vec::new();
+    vec.extend_from_slice(FORM_ID);
+    // Patched once everything is in
+    vec.extend_from_slice(&0u32.to_be_bytes());
+    vec.extend_from_slice(if is_aifc { AIFC_TYPE } else { AIFF_TYPE });
+
+    if is_aifc {
+        push_chunk(
+            &mut vec,
+            FVER_TYPE,
+            &AIFC_VERSION.to_be_bytes(),
+            ByteOrder::BigEndian,
+        );
+    }
+
+    let mut comm = Vec::new();
+    comm.extend_from_slice(&format.get_channels().to_be_bytes());
+    comm.extend_from_slice(&(wav.len_frames() as u32).to_be_bytes());
+    let bits_per_sample = match encoding {
+        Encoding::ALAW | Encoding::MULAW => 16,
+        Encoding::LPCM => format.get_valid_bits_per_sample(),
+        Encoding::IEEE => format.get_bits_per_sample(),
+    };
+    comm.extend_from_slice(&bits_per_sample.to_be_bytes());
+    comm.extend_from_slice(&to_extended(format.get_sample_rate() as f64));
+    if is_aifc {
+        let (id, name) = compression(encoding, sample_size, byte_order);
+        comm.extend_from_slice(id);
+        // Pascal string, padded to an even length
+        comm.push(name.len() as u8);
+        comm.extend_from_slice(name);
+        if name.len() % 2 == 0 {
+            comm.push(0);
+        }
+    }
+    push_chunk(&mut vec, COMM_TYPE, &comm, ByteOrder::BigEndian);
+
+    for chunk in wav.chunks() {
+        let id = chunk
+            .id()
+            .get_four_cc()
+            .ok_or(Error::GuidChunk(chunk.get_guid()))?;
+        push_chunk(&mut vec, id.as_array(), chunk.data(), ByteOrder::BigEndian);
+    }
+
+    // No offset, no block alignment
+    let mut ssnd = vec![0; 8];
+    let mut body = wav.encode_body()?;
+    to_wav_layout(&mut body, encoding, sample_size, byte_order);
+    ssnd.extend_from_slice(&body);
+    push_chunk(&mut vec, SSND_TYPE, &ssnd, ByteOrder::BigEndian);
+
+    let form_size = u32::try_from(vec.len() - 8).map_err(|_| Error::SizeMismatch {
+        id: FourCC::new(*FORM_ID),
+        expected: u32::MAX as u64,
+        found: vec.len() as u64 - 8,
+    })?;
+    vec[4..8].copy_from_slice(&form_size.to_be_bytes());
+
+    Ok(vec)
+}
+
+// Swaps samples between AIFF (or .au) and WAV layouts, it works the same both ways:
+// byte order of multi-byte samples, and signed against offset 8-bit samples
+pub(crate) fn to_wav_layout(
+    bytes: &mut [u8],
+    encoding: Encoding,
+    sample_size: usize,
+    byte_order: ByteOrder,
+) {
+    match encoding {
+        Encoding::ALAW | Encoding::MULAW => {}
+        Encoding::LPCM if sample_size == 1 => bytes.iter_mut().for_each(|byte| *byte ^= 0x80),
+        _ if byte_order == ByteOrder::BigEndian => swap_bytes(bytes, sample_size),
+        _ => {}
+    }
+}
+
+// 80-bit IEEE 754 extended precision: sign and 15-bit exponent, then a 64-bit mantissa
+// with an explicit integer bit. Every f64 fits exactly.
+fn to_extended(value: f64) -> [u8; 10] {
+    let mut extended = [0; 10];
+    if value == 0.0 || !value.is_finite() {
+        return extended;
+    }
+
+    let bits = value.abs().to_bits();
+    let mut exponent = ((bits >> 52) & 0x7ff) as i32 - 1023;
+    let mut mantissa = bits & ((1 << 52) - 1);
+    if exponent == -1023 {
+        // Subnormal, normalise the mantissa
+        let shift = mantissa.leading_zeros() as i32 - 11;
+        mantissa <<= shift;
+        exponent -= shift - 1;
+    } else {
+        mantissa |= 1 << 52;
+    }
+
+    let sign = if value < 0.0 { 0x8000 } else { 0 };
+    extended[..2].copy_from_slice(&(sign | (exponent + 16_383) as u16).to_be_bytes());
+    extended[2..].copy_from_slice(&(mantissa << 11).to_be_bytes());
+
+    extended
+}
+
+fn from_extended(extended: [u8; 10]) -> f64 {
+    let sign_exponent = u16::from_be_bytes([extended[0], extended[1]]);
+    let mut mantissa = [0; 8];
+    mantissa.copy_from_slice(&extended[2..]);
+    let mantissa = u64::from_be_bytes(mantissa);
+
+    let exponent = (sign_exponent & 0x7fff) as i32 - 16_383 - 63;
+    let value = mantissa as f64 * 2f64.powi(exponent);
+
+    if sign_exponent & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codable::{Decodable, Encodable},
+        convert::Dither,
+    };
+
+    #[test]
+    fn extended_sample_rate() {
+        let extended = to_extended(44_100.0);
+
+        assert_eq!(extended, [0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(from_extended(extended), 44_100.0);
+        assert_eq!(from_extended(to_extended(8_000.0)), 8_000.0);
+        assert_eq!(from_extended(to_extended(0.0)), 0.0);
+    }
+
+    #[test]
+    fn round_trips_aiff() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 44_100);
+        wav.set_container(Container::Aiff);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"NAME"), b"tone".to_vec()));
+        wav.push_body(vec![0x0102, -2, 3, -4]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(encoded[..12], *b"FORM\0\0\0\x42AIFF");
+        // COMM: 2 channels, 2 frames, 16 bits, 44.1 kHz
+        assert_eq!(
+            encoded[12..38],
+            *b"COMM\0\0\0\x12\0\x02\0\0\0\x02\0\x10\x40\x0e\xac\x44\0\0\0\0\0\0"
+        );
+        // Samples are big-endian
+        assert_eq!(encoded[encoded.len() - 8..encoded.len() - 6], [0x01, 0x02]);
+
+        let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.container(), Container::Aiff);
+        assert_eq!(decoded.format(), wav.format());
+        assert_eq!(decoded.chunks(), wav.chunks());
+        assert_eq!(decoded.body(), wav.body());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn round_trips_aifc() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 48_000);
+        wav.set_container(Container::AiffC(ByteOrder::LittleEndian));
+        wav.push_body(vec![0x0102, -2]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(encoded[8..12], *AIFC_TYPE);
+        assert_eq!(encoded[50..54], *b"sowt");
+        assert_eq!(encoded[encoded.len() - 4..], [0x02, 0x01, 0xfe, 0xff]);
+
+        let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.container(), wav.container());
+        assert_eq!(decoded.body(), wav.body());
+
+        let mut wav = Wav::<f32>::new(Encoding::IEEE, 1, 48_000);
+        wav.set_container(Container::Aiff);
+        wav.push_body(vec![0.5, -0.25]);
+
+        let decoded = Wav::<f32>::decode_new(&wav.encode().unwrap()[..]).unwrap();
+        assert_eq!(decoded.container(), Container::AiffC(ByteOrder::BigEndian));
+        assert_eq!(decoded.format().get_encoding(), Encoding::IEEE);
+        assert_eq!(decoded.body(), wav.body());
+    }
+
+    #[test]
+    fn converts_sowt_to_float() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 48_000);
+        wav.set_container(Container::AiffC(ByteOrder::LittleEndian));
+        wav.push_body(vec![0x4000, -0x2000]);
+        let sowt = Wav::<i16>::decode_new(&wav.encode().unwrap()[..]).unwrap();
+
+        // Float samples are always big-endian fl32
+        let float = sowt.convert::<f32>(Dither::None).unwrap();
+        let encoded = float.encode().unwrap();
+        assert_eq!(encoded[50..54], *b"fl32");
+
+        let decoded = Wav::<f32>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.body(), [0.5, -0.25]);
+    }
+
+    #[test]
+    fn signed_8_bit_samples() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::Aiff);
+        wav.push_body(vec![0, 128, 255]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(
+            encoded[encoded.len() - 4..encoded.len() - 1],
+            [0x80, 0x00, 0x7f]
+        );
+
+        let decoded = Wav::<u8>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.body(), [0, 128, 255]);
+    }
+
+    #[test]
+    fn rejects_unknown_compression() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::AiffC(ByteOrder::BigEndian));
+        let mut encoded = wav.encode().unwrap();
+        encoded[50..54].copy_from_slice(b"ima4");
+
+        assert!(matches!(
+            Wav::<i16>::decode_new(&encoded[..]),
+            Err(Error::UnsupportedCompression(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_comm() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::Aiff);
+        wav.push_body(vec![1, 2]);
+        let encoded = wav.encode().unwrap();
+
+        // COMM numChannels, then sampleSize
+        for (at, field) in [(20, "channels"), (26, "bits_per_sample")] {
+            let mut broken = encoded.clone();
+            broken[at..at + 2].fill(0);
+            assert!(matches!(
+                Wav::<i16>::decode_new(&broken[..]),
+                Err(Error::InvalidFormat { field: found, .. }) if found == field
+            ));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    aiff::to_wav_layout,
+    codable::{read_exact, read_partial},
+    container::Container,
+    error::Error,
+    four_cc::FourCC,
+    header_format::{Encoding, HeaderFormat},
+    sample::{ByteOrder, Sample},
+    wav::Wav,
+};
+use std::io::{self, Read};
+
+// Sun/NeXT .au: a 24 byte big-endian header, a free-form annotation, then the samples.
+// Samples are big-endian, 8-bit ones signed, the same as AIFF.
+
+pub(crate) const AU_MAGIC: &[u8; 4] = b".snd";
+
+const HEADER_SIZE: u32 = 24;
+// Data size when the writer didn't know it, samples run to the end of the file
+const UNKNOWN_SIZE: u32 = u32::MAX;
+
+// Encoding codes and the sample size they imply, for the ones we can hold in a Wav
+fn encoding_of(code: u32) -> Option<(Encoding, usize)> {
+    Some(match code {
+        1 => (Encoding::MULAW, 1),
+        2 => (Encoding::LPCM, 1),
+        3 => (Encoding::LPCM, 2),
+        4 => (Encoding::LPCM, 3),
+        5 => (Encoding::LPCM, 4),
+        6 => (Encoding::IEEE, 4),
+        7 => (Encoding::IEEE, 8),
+        27 => (Encoding::ALAW, 1),
+        _ => return None,
+    })
+}
+
+fn code_of(encoding: Encoding, sample_size: usize) -> u32 {
+    match (encoding, sample_size) {
+        (Encoding::MULAW, _) => 1,
+        (Encoding::ALAW, _) => 27,
+        (Encoding::IEEE, 8) => 7,
+        (Encoding::IEEE, _) => 6,
+        (Encoding::LPCM, size) => size as u32 + 1,
+    }
+}
+
+pub(crate) fn decode<R: Read, T: Sample>(mut reader: R) -> Result<Wav<T>, Error> {
+    let mut header = [0; HEADER_SIZE as usize];
+    read_exact(&mut reader, &mut header, AU_MAGIC)?;
+
+    let field = |index: usize| {
+        u32::from_be_bytes(
+            header[index * 4..index * 4 + 4]
+                .try_into()
+                .expect("4 bytes"),
+        )
+    };
+    if header[..4] != *AU_MAGIC {
+        return Err(Error::BadMagic {
+            expected: FourCC::new(*AU_MAGIC),
+            found: FourCC::new(header[..4].try_into().expect("4 bytes")),
+        });
+    }
+    let (offset, data_size, code, sample_rate, channels) =
+        (field(1), field(2), field(3), field(4), field(5));
+
+    let (encoding, sample_size) = encoding_of(code).ok_or(Error::UnsupportedAuEncoding(code))?;
+    if offset < HEADER_SIZE {
+        return Err(Error::InvalidFormat {
+            field: "data offset",
+            expected: HEADER_SIZE as u64,
+            found: offset as u64,
+        });
+    }
+
+    let format = HeaderFormat::new(
+        encoding,
+        channels as usize,
+        sample_rate as usize,
+        sample_size,
+    );
+    format.check()?;
+
+    // The annotation has no counterpart in a Wav and is skipped
+    io::copy(
+        &mut (&mut reader).take((offset - HEADER_SIZE) as u64),
+        &mut io::sink(),
+    )?;
+
+    let mut body = match data_size {
+        UNKNOWN_SIZE => read_partial(reader, u64::MAX)?,
+        size => read_partial(reader, size as u64)?,
+    };
+    to_wav_layout(&mut body, encoding, sample_size, ByteOrder::BigEndian);
+
+    Wav::from_parts(Container::Au, format, None, Vec::new(), &body)
+}
+
+pub(crate) fn encode<T: Sample>(wav: &Wav<T>) -> Result<Vec<u8>, Error> {
+    let format = wav.format();
+    let encoding = format.get_encoding();
+    let sample_size = format.get_sample_size();
+
+    let mut body = wav.encode_body()?;
+    to_wav_layout(&mut body, encoding, sample_size, ByteOrder::BigEndian);
+    let data_size = u32::try_from(body.len())
+        .ok()
+        .filter(|size| *size != UNKNOWN_SIZE)
+        .ok_or(Error::SizeMismatch {
+            id: FourCC::new(*AU_MAGIC),
+            expected: UNKNOWN_SIZE as u64 - 1,
+            found: body.len() as u64,
+        })?;
+
+    let mut vec = Vec::new();
+    vec.extend_from_slice(AU_MAGIC);
+    // Header and an empty, null terminated annotation of the minimum 4 bytes
+    vec.extend_from_slice(&(HEADER_SIZE + 4).to_be_bytes());
+    vec.extend_from_slice(&data_size.to_be_bytes());
+    vec.extend_from_slice(&code_of(encoding, sample_size).to_be_bytes());
+    vec.extend_from_slice(&format.get_sample_rate().to_be_bytes());
+    vec.extend_from_slice(&(format.get_channels() as u32).to_be_bytes());
+    vec.extend_from_slice(&[0; 4]);
+    vec.extend_from_slice(&body);
+
+    Ok(vec)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codable::{Decodable, Encodable};
+
+    #[test]
+    fn round_trip() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.set_container(Container::Au);
+        wav.push_body(vec![0x0102, -2, 3, -4]);
+
+        let encoded = wav.encode().unwrap();
+        // Offset 28, 8 bytes of 16-bit linear at 8 kHz, 2 channels
+        assert_eq!(
+            encoded[..24],
+            *b".snd\0\0\0\x1c\0\0\0\x08\0\0\0\x03\0\0\x1f\x40\0\0\0\x02"
+        );
+        assert_eq!(encoded[28..30], [0x01, 0x02]);
+
+        let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.container(), Container::Au);
+        assert_eq!(decoded.format(), wav.format());
+        assert_eq!(decoded.body(), wav.body());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn companded_with_unknown_size() {
+        let mut wav = Wav::<i16>::new(Encoding::ALAW, 1, 8_000);
+        wav.set_container(Container::Au);
+        wav.push_body(vec![1_000, -1_000, 8]);
+
+        let mut encoded = wav.encode().unwrap();
+        assert_eq!(encoded[12..16], 27u32.to_be_bytes());
+        encoded[8..12].copy_from_slice(&UNKNOWN_SIZE.to_be_bytes());
+        // Annotation grown to 8 bytes
+        encoded[4..8].copy_from_slice(&32u32.to_be_bytes());
+        encoded.splice(28..28, [b'h', b'i', 0, 0]);
+
+        let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.format().get_encoding(), Encoding::ALAW);
+        // Companding is lossy, compare the stored bytes
+        assert_eq!(
+            decoded.encode_raw(ByteOrder::BigEndian).unwrap(),
+            wav.encode_raw(ByteOrder::BigEndian).unwrap()
+        );
+    }
+
+    #[test]
+    fn signed_8_bit_samples() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::Au);
+        wav.push_body(vec![0, 128, 255]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(encoded[28..], [0x80, 0x00, 0x7f]);
+        assert_eq!(
+            Wav::<u8>::decode_new(&encoded[..]).unwrap().body(),
+            wav.body()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_encoding() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::Au);
+        let mut encoded = wav.encode().unwrap();
+        // G.721 ADPCM
+        encoded[12..16].copy_from_slice(&23u32.to_be_bytes());
+
+        assert!(matches!(
+            Wav::<i16>::decode_new(&encoded[..]),
+            Err(Error::UnsupportedAuEncoding(23))
+        ));
+
+        // No channels
+        encoded[12..16].copy_from_slice(&3u32.to_be_bytes());
+        encoded[20..24].fill(0);
+        assert!(matches!(
+            Wav::<i16>::decode_new(&encoded[..]),
+            Err(Error::InvalidFormat {
+                field: "channels",
+                ..
+            })
+        ));
+    }
+}
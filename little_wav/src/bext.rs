@@ -0,0 +1,324 @@
+use crate::{
+    chunk::{decode_chunk, read_text, Chunk, RawChunk},
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
+};
+use std::io::Read;
+
+pub(crate) const BEXT_TYPE: &[u8; 4] = b"bext";
+
+// Fixed part of the chunk, the coding history follows
+const FIXED_SIZE: usize = 602;
+
+// Loudness fields of bext version 2, in hundredths of LUFS, LU or dB
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Loudness {
+    pub value: i16,
+    pub range: i16,
+    pub max_true_peak: i16,
+    pub max_momentary: i16,
+    pub max_short_term: i16,
+}
+
+impl Loudness {
+    // What a field holds when it wasn't measured
+    pub const UNKNOWN: i16 = 0x7fff;
+}
+
+impl Default for Loudness {
+    fn default() -> Self {
+        Self {
+            value: Self::UNKNOWN,
+            range: Self::UNKNOWN,
+            max_true_peak: Self::UNKNOWN,
+            max_momentary: Self::UNKNOWN,
+            max_short_term: Self::UNKNOWN,
+        }
+    }
+}
+
+// Broadcast Wave Format extension chunk, EBU Tech 3285
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bext {
+    description: String,
+    originator: String,
+    originator_reference: String,
+    // yyyy-mm-dd and hh:mm:ss
+    origination_date: String,
+    origination_time: String,
+    // First sample of the file, counted in samples since midnight
+    time_reference: u64,
+    version: u16,
+    umid: [u8; 64],
+    loudness: Loudness,
+    coding_history: String,
+}
+
+impl Default for Bext {
+    fn default() -> Self {
+        Self {
+            description: String::new(),
+            originator: String::new(),
+            originator_reference: String::new(),
+            origination_date: String::new(),
+            origination_time: String::new(),
+            time_reference: 0,
+            version: 2,
+            umid: [0; 64],
+            loudness: Loudness::default(),
+            coding_history: String::new(),
+        }
+    }
+}
+
+impl Bext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Text fields are ASCII, cut to their fixed size on encode
+    pub fn description(&mut self, description: &str) -> &Self {
+        self.description = description.to_string();
+        self
+    }
+    pub fn originator(&mut self, originator: &str) -> &Self {
+        self.originator = originator.to_string();
+        self
+    }
+    pub fn originator_reference(&mut self, originator_reference: &str) -> &Self {
+        self.originator_reference = originator_reference.to_string();
+        self
+    }
+    pub fn origination_date(&mut self, origination_date: &str) -> &Self {
+        self.origination_date = origination_date.to_string();
+        self
+    }
+    pub fn origination_time(&mut self, origination_time: &str) -> &Self {
+        self.origination_time = origination_time.to_string();
+        self
+    }
+    pub fn time_reference(&mut self, time_reference: u64) -> &Self {
+        self.time_reference = time_reference;
+        self
+    }
+    pub fn version(&mut self, version: u16) -> &Self {
+        self.version = version;
+        self
+    }
+    pub fn umid(&mut self, umid: [u8; 64]) -> &Self {
+        self.umid = umid;
+        self
+    }
+    pub fn loudness(&mut self, loudness: Loudness) -> &Self {
+        self.loudness = loudness;
+        self
+    }
+    pub fn coding_history(&mut self, coding_history: &str) -> &Self {
+        self.coding_history = coding_history.to_string();
+        self
+    }
+
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+    pub fn get_originator(&self) -> &str {
+        &self.originator
+    }
+    pub fn get_originator_reference(&self) -> &str {
+        &self.originator_reference
+    }
+    pub fn get_origination_date(&self) -> &str {
+        &self.origination_date
+    }
+    pub fn get_origination_time(&self) -> &str {
+        &self.origination_time
+    }
+    pub fn get_time_reference(&self) -> u64 {
+        self.time_reference
+    }
+    pub fn get_version(&self) -> u16 {
+        self.version
+    }
+    pub fn get_umid(&self) -> &[u8; 64] {
+        &self.umid
+    }
+    pub fn get_loudness(&self) -> Loudness {
+        self.loudness
+    }
+    pub fn get_coding_history(&self) -> &str {
+        &self.coding_history
+    }
+
+    pub(crate) fn is_bext(chunk: &RawChunk) -> bool {
+        chunk.id() == FourCC::new(*BEXT_TYPE)
+    }
+
+    pub(crate) fn from_chunk(chunk: &RawChunk) -> Result<Self, Error> {
+        decode_chunk(FourCC::new(*BEXT_TYPE), chunk.data())
+    }
+    pub(crate) fn to_chunk(&self) -> RawChunk {
+        let mut body = Vec::with_capacity(FIXED_SIZE + self.coding_history.len());
+
+        push_text(&mut body, &self.description, 256);
+        push_text(&mut body, &self.originator, 32);
+        push_text(&mut body, &self.originator_reference, 32);
+        push_text(&mut body, &self.origination_date, 10);
+        push_text(&mut body, &self.origination_time, 8);
+        body.extend_from_slice(&self.time_reference.to_le_bytes());
+        body.extend_from_slice(&self.version.to_le_bytes());
+        body.extend_from_slice(&self.umid);
+        for field in [
+            self.loudness.value,
+            self.loudness.range,
+            self.loudness.max_true_peak,
+            self.loudness.max_momentary,
+            self.loudness.max_short_term,
+        ] {
+            body.extend_from_slice(&field.to_le_bytes());
+        }
+        // Reserved
+        body.resize(FIXED_SIZE, 0);
+        body.extend_from_slice(self.coding_history.as_bytes());
+
+        RawChunk::new(FourCC::new(*BEXT_TYPE), body)
+    }
+}
+
+// Null padded to `size` bytes, or cut short when longer
+fn push_text(vec: &mut Vec<u8>, text: &str, size: usize) {
+    let bytes = &text.as_bytes()[..text.len().min(size)];
+    vec.extend_from_slice(bytes);
+    vec.resize(vec.len() + size - bytes.len(), 0);
+}
+
+impl Chunk for Bext {
+    fn get_be_id(&self) -> &[u8; 4] {
+        BEXT_TYPE
+    }
+
+    fn get_le_size(&self) -> u32 {
+        (FIXED_SIZE + self.coding_history.len()) as u32
+    }
+}
+
+impl Encodable for Bext {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        self.to_chunk().encode()
+    }
+}
+
+impl Decodable for Bext {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut buffer_16 = [0; 4];
+
+        read_exact(&mut reader, &mut buffer_16, BEXT_TYPE)?;
+        if buffer_16 != *BEXT_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*BEXT_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
+        read_exact(&mut reader, &mut buffer_16, BEXT_TYPE)?;
+        let size = u32::from_le_bytes(buffer_16) as usize;
+        if size < FIXED_SIZE {
+            return Err(Error::SizeMismatch {
+                id: FourCC::new(*BEXT_TYPE),
+                expected: FIXED_SIZE as u64,
+                found: size as u64,
+            });
+        }
+
+        // Read up to the declared size rather than trusting it for an allocation
+        let mut body = Vec::new();
+        reader.take(size as u64).read_to_end(&mut body)?;
+        if body.len() < size {
+            return Err(Error::TruncatedChunk(FourCC::new(*BEXT_TYPE)));
+        }
+
+        let u16_at = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+        let loudness = Loudness {
+            value: u16_at(412) as i16,
+            range: u16_at(414) as i16,
+            max_true_peak: u16_at(416) as i16,
+            max_momentary: u16_at(418) as i16,
+            max_short_term: u16_at(420) as i16,
+        };
+        let version = u16_at(346);
+
+        Ok(Self {
+            description: read_text(&body[..256]),
+            originator: read_text(&body[256..288]),
+            originator_reference: read_text(&body[288..320]),
+            origination_date: read_text(&body[320..330]),
+            origination_time: read_text(&body[330..338]),
+            time_reference: u64::from_le_bytes(body[338..346].try_into().expect("8 bytes")),
+            version,
+            umid: body[348..412].try_into().expect("64 bytes"),
+            // Reserved before version 2, and likely left as zeroes
+            loudness: if version >= 2 {
+                loudness
+            } else {
+                Loudness::default()
+            },
+            coding_history: read_text(&body[FIXED_SIZE..]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut bext = Bext::new();
+        bext.description("Take 3");
+        bext.originator("little_wav");
+        bext.origination_date("2024-05-01");
+        bext.origination_time("12:30:00");
+        bext.time_reference(48_000 * 3_600 * 12);
+        bext.loudness(Loudness {
+            value: -2_300,
+            ..Loudness::default()
+        });
+        bext.coding_history("A=PCM,F=48000,W=24,M=stereo\r\n");
+
+        let encoded = bext.encode().unwrap();
+        assert_eq!(encoded[..8], *b"bext\x77\x02\0\0");
+        assert_eq!(encoded[8..14], *b"Take 3");
+        assert_eq!(encoded[346..354], (48_000u64 * 3_600 * 12).to_le_bytes());
+        assert_eq!(encoded[420..422], (-2_300i16).to_le_bytes());
+
+        let decoded = Bext::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded, bext);
+    }
+
+    #[test]
+    fn cuts_long_text() {
+        let mut bext = Bext::new();
+        bext.origination_date("2024-05-01T12:30:00");
+
+        let decoded = Bext::decode_new(&bext.encode().unwrap()[..]).unwrap();
+        assert_eq!(decoded.get_origination_date(), "2024-05-01");
+        assert_eq!(decoded.get_origination_time(), "");
+    }
+
+    #[test]
+    fn rejects_short_chunk() {
+        let chunk = RawChunk::new(FourCC::new(*BEXT_TYPE), vec![0; 100]);
+
+        assert!(matches!(
+            Bext::from_chunk(&chunk),
+            Err(Error::SizeMismatch { .. })
+        ));
+
+        // Claims 4 GiB but holds only the fixed fields
+        let mut encoded = Bext::new().encode().unwrap();
+        encoded[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Bext::decode_new(&encoded[..]),
+            Err(Error::TruncatedChunk(_))
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+use std::ops::{BitOr, BitOrAssign};
+
+// dwChannelMask of WAVE_FORMAT_EXTENSIBLE: which speaker each channel feeds, in channel order
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+pub struct ChannelMask(u32);
+
+impl ChannelMask {
+    pub const FRONT_LEFT: ChannelMask = ChannelMask(0x1);
+    pub const FRONT_RIGHT: ChannelMask = ChannelMask(0x2);
+    pub const FRONT_CENTER: ChannelMask = ChannelMask(0x4);
+    pub const LOW_FREQUENCY: ChannelMask = ChannelMask(0x8);
+    pub const BACK_LEFT: ChannelMask = ChannelMask(0x10);
+    pub const BACK_RIGHT: ChannelMask = ChannelMask(0x20);
+    pub const FRONT_LEFT_OF_CENTER: ChannelMask = ChannelMask(0x40);
+    pub const FRONT_RIGHT_OF_CENTER: ChannelMask = ChannelMask(0x80);
+    pub const BACK_CENTER: ChannelMask = ChannelMask(0x100);
+    pub const SIDE_LEFT: ChannelMask = ChannelMask(0x200);
+    pub const SIDE_RIGHT: ChannelMask = ChannelMask(0x400);
+    pub const TOP_CENTER: ChannelMask = ChannelMask(0x800);
+    pub const TOP_FRONT_LEFT: ChannelMask = ChannelMask(0x1000);
+    pub const TOP_FRONT_CENTER: ChannelMask = ChannelMask(0x2000);
+    pub const TOP_FRONT_RIGHT: ChannelMask = ChannelMask(0x4000);
+    pub const TOP_BACK_LEFT: ChannelMask = ChannelMask(0x8000);
+    pub const TOP_BACK_CENTER: ChannelMask = ChannelMask(0x10000);
+    pub const TOP_BACK_RIGHT: ChannelMask = ChannelMask(0x20000);
+
+    // No speaker assignment at all, e.g. ambisonic B-format
+    pub const DIRECTOUT: ChannelMask = ChannelMask(0);
+    pub const MONO: ChannelMask = Self::FRONT_CENTER;
+    pub const STEREO: ChannelMask = ChannelMask(0x3);
+    pub const SURROUND_5_1: ChannelMask = ChannelMask(0x3f);
+    pub const SURROUND_7_1: ChannelMask = ChannelMask(0x63f);
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+    pub fn contains(self, other: ChannelMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+    // Number of speakers taking part
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+    // Single-speaker masks, in channel order
+    pub fn positions(self) -> Vec<ChannelMask> {
+        (0..32)
+            .map(|bit| ChannelMask(1 << bit))
+            .filter(|position| self.contains(*position))
+            .collect()
+    }
+}
+
+impl BitOr for ChannelMask {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ChannelMask {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
@@ -1,7 +1,262 @@
-// WAV file consists of 4 chunks: RIFF, fmt, data, and fact.
+use crate::{
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
+    guid::Guid,
+    sample::ByteOrder,
+};
+use std::{fmt, io::Read};
+
+// WAV file is a RIFF container: a list of chunks such as fmt, data, fact, LIST...
 pub trait Chunk {
     // Get the chunk ID as a big-endian array of 4 bytes
     fn get_be_id(&self) -> &[u8; 4];
     // Get the chunk size as a little-endian u32
     fn get_le_size(&self) -> u32;
+
+    // Chunk ID in Wave64 files, which name chunks by GUID
+    fn get_guid(&self) -> Guid {
+        Guid::from_four_cc(*self.get_be_id())
+    }
+
+    // Chunk bodies are word-aligned, odd sizes are followed by a pad byte
+    fn get_padded_size(&self) -> u64 {
+        padded(self.get_le_size() as u64)
+    }
+}
+
+// Saturates for sizes near u64::MAX, which no file holds anyway
+pub(crate) fn padded(size: u64) -> u64 {
+    size.saturating_add(size % 2)
+}
+
+// Appends a chunk with its 32-bit size in `order`. Sizes past u32 are caught by the size
+// check of the chunk or file holding it.
+pub(crate) fn push_chunk(vec: &mut Vec<u8>, id: &[u8; 4], body: &[u8], order: ByteOrder) {
+    let size = body.len() as u32;
+    let size = match order {
+        ByteOrder::BigEndian => size.to_be_bytes(),
+        ByteOrder::LittleEndian => size.to_le_bytes(),
+    };
+    vec.extend_from_slice(id);
+    push_padded(vec, &size, body, padded);
+}
+
+// Appends the rest of a chunk header and the body, then pads `vec` to the length `padded` gives
+pub(crate) fn push_padded(vec: &mut Vec<u8>, header: &[u8], body: &[u8], padded: fn(u64) -> u64) {
+    vec.extend_from_slice(header);
+    vec.extend_from_slice(body);
+    vec.resize(padded(vec.len() as u64) as usize, 0);
+}
+
+// Text of a fixed size field, up to the first null
+pub(crate) fn read_text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+// Header decoders read a whole chunk, header included
+pub(crate) fn decode_chunk<C: Decodable>(id: FourCC, body: &[u8]) -> Result<C, Error> {
+    let header = ChunkHeader::new(id, body.len() as u32).encode()?;
+    C::decode_new((&header[..]).chain(body))
+}
+
+// Generic 8 byte chunk header: ID followed by the body size
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkHeader {
+    id: FourCC,
+    size: u32,
+}
+
+impl ChunkHeader {
+    pub fn new(id: FourCC, size: u32) -> Self {
+        Self { id, size }
+    }
+    pub fn id(&self) -> FourCC {
+        self.id
+    }
+}
+
+impl Chunk for ChunkHeader {
+    fn get_be_id(&self) -> &[u8; 4] {
+        self.id.as_array()
+    }
+
+    fn get_le_size(&self) -> u32 {
+        self.size
+    }
+}
+
+impl Encodable for ChunkHeader {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut vec = Vec::new();
+
+        vec.extend_from_slice(self.get_be_id());
+        vec.extend_from_slice(&self.size.to_le_bytes());
+
+        Ok(vec)
+    }
+}
+
+impl Decodable for ChunkHeader {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut buffer_16 = [0; 4];
+
+        read_exact(&mut reader, &mut buffer_16, b"    ")?;
+        let id = FourCC::new(buffer_16);
+
+        read_exact(&mut reader, &mut buffer_16, id.as_array())?;
+        let size = u32::from_le_bytes(buffer_16);
+
+        Ok(Self { id, size })
+    }
+}
+
+// Name of a chunk: a FourCC in RIFF, a GUID in Wave64.
+// GUIDs derived from a FourCC are always kept as the FourCC, so both containers can hold them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChunkId {
+    FourCC(FourCC),
+    Guid(Guid),
+}
+
+impl ChunkId {
+    pub fn from_guid(guid: Guid) -> Self {
+        match guid.get_four_cc() {
+            Some(id) => ChunkId::FourCC(id),
+            None => ChunkId::Guid(guid),
+        }
+    }
+
+    pub fn get_four_cc(&self) -> Option<FourCC> {
+        match self {
+            ChunkId::FourCC(id) => Some(*id),
+            ChunkId::Guid(_) => None,
+        }
+    }
+    pub fn get_guid(&self) -> Guid {
+        match self {
+            ChunkId::FourCC(id) => Guid::from_four_cc(*id.as_array()),
+            ChunkId::Guid(guid) => *guid,
+        }
+    }
+}
+
+impl From<FourCC> for ChunkId {
+    fn from(id: FourCC) -> Self {
+        ChunkId::FourCC(id)
+    }
+}
+
+impl PartialEq<FourCC> for ChunkId {
+    fn eq(&self, other: &FourCC) -> bool {
+        self.get_four_cc() == Some(*other)
+    }
+}
+
+impl fmt::Display for ChunkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkId::FourCC(id) => write!(f, "{}", id),
+            ChunkId::Guid(guid) => write!(f, "{}", guid),
+        }
+    }
+}
+
+// Chunk we don't interpret, kept as is so it survives a read/write round-trip
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawChunk {
+    id: ChunkId,
+    data: Vec<u8>,
+}
+
+impl RawChunk {
+    pub fn new(id: impl Into<ChunkId>, data: Vec<u8>) -> Self {
+        Self {
+            id: id.into(),
+            data,
+        }
+    }
+    pub fn id(&self) -> ChunkId {
+        self.id
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn into_parts(self) -> (ChunkId, Vec<u8>) {
+        (self.id, self.data)
+    }
+}
+
+impl Chunk for RawChunk {
+    // GUID-only chunks have no FourCC, their first 4 bytes stand in for it
+    fn get_be_id(&self) -> &[u8; 4] {
+        match &self.id {
+            ChunkId::FourCC(id) => id.as_array(),
+            ChunkId::Guid(guid) => guid.as_array()[..4]
+                .try_into()
+                .expect("a GUID holds more than 4 bytes"),
+        }
+    }
+
+    fn get_le_size(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn get_guid(&self) -> Guid {
+        self.id.get_guid()
+    }
+}
+
+impl Encodable for RawChunk {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let id = match self.id {
+            ChunkId::FourCC(id) => id,
+            ChunkId::Guid(guid) => return Err(Error::GuidChunk(guid)),
+        };
+        if self.data.len() > u32::MAX as usize {
+            return Err(Error::SizeMismatch {
+                id,
+                expected: u32::MAX as u64,
+                found: self.data.len() as u64,
+            });
+        }
+
+        let mut vec = ChunkHeader::new(id, self.get_le_size()).encode()?;
+        vec.extend_from_slice(&self.data);
+        if self.data.len() % 2 == 1 {
+            vec.push(0);
+        }
+
+        Ok(vec)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_pads_odd_chunks() {
+        let chunk = RawChunk::new(FourCC::new(*b"JUNK"), vec![1, 2, 3]);
+        let encoded = chunk.encode().unwrap();
+
+        assert_eq!(encoded, [74, 85, 78, 75, 3, 0, 0, 0, 1, 2, 3, 0]);
+        assert_eq!(chunk.get_padded_size(), 4);
+    }
+
+    #[test]
+    fn guid_chunks_stay_out_of_riff() {
+        let guid = Guid::new([7; 16]);
+        let chunk = RawChunk::new(ChunkId::from_guid(guid), vec![1]);
+
+        assert_eq!(chunk.get_guid(), guid);
+        assert!(matches!(chunk.encode(), Err(Error::GuidChunk(_))));
+
+        let id = ChunkId::from_guid(Guid::from_four_cc(*b"LIST"));
+        assert_eq!(id, FourCC::new(*b"LIST"));
+    }
 }
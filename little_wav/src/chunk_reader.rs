@@ -0,0 +1,292 @@
+use crate::{
+    chunk::{padded, Chunk, ChunkHeader, RawChunk},
+    codable::{read_partial, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
+    header_ds64::{HeaderDs64, DS64_TYPE},
+    header_riff::HeaderRiff,
+    sample::ByteOrder,
+};
+use std::io::{self, Read, Seek, SeekFrom};
+
+// Walks the chunks of a RIFF body one by one, in whatever order they come
+pub struct ChunkReader<R: Read> {
+    reader: R,
+    // Bytes of the RIFF body not consumed yet
+    remaining: u64,
+    // Bytes of the current chunk body (with pad) not consumed yet
+    pending: u64,
+    current: Option<ChunkHeader>,
+    // Body size of the current chunk, from ds64 when its header holds 0xFFFFFFFF
+    size: u64,
+    ds64: Option<HeaderDs64>,
+    // Of the chunk sizes, AIFF ones are big-endian
+    byte_order: ByteOrder,
+}
+
+impl<R: Read> ChunkReader<R> {
+    // `size` is the RIFF body size following the form type
+    pub fn new(reader: R, size: u64) -> Self {
+        Self {
+            reader,
+            remaining: size,
+            pending: 0,
+            current: None,
+            size: 0,
+            ds64: None,
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    pub fn with_byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    // Reads the RIFF header, and for RF64 and BW64 the ds64 chunk that has to follow it
+    pub(crate) fn open(mut reader: R) -> Result<(HeaderRiff, Option<HeaderDs64>, Self), Error> {
+        let mut riff = HeaderRiff::decode_new(&mut reader)?;
+        // RIFF size also counts the 4 bytes of form type we have just read
+        let mut walker = Self::new(reader, (riff.get_le_size() as u64).saturating_sub(4));
+
+        if !riff.get_form().is_64_bit() {
+            return Ok((riff, None, walker));
+        }
+
+        let header = walker
+            .next_header()?
+            .filter(|header| header.get_be_id() == DS64_TYPE)
+            .ok_or(Error::MissingChunk(FourCC::new(*DS64_TYPE)))?;
+        let body = walker.read_body()?;
+        let encoded_header = header.encode()?;
+        let ds64 = HeaderDs64::decode_new((&encoded_header[..]).chain(&body[..]))?;
+
+        riff.resize(ds64.get_riff_size());
+        walker.remaining = ds64
+            .get_riff_size()
+            .saturating_sub(4 + 8 + header.get_padded_size());
+        walker.ds64 = Some(ds64.clone());
+
+        Ok((riff, Some(ds64), walker))
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Moves on to the next chunk header, skipping whatever is left of the current one.
+    // Returns None at the end of the RIFF body or at a clean EOF between chunks.
+    pub fn next_header(&mut self) -> Result<Option<ChunkHeader>, Error> {
+        self.skip_body()?;
+        self.current = None;
+
+        if self.remaining < 8 {
+            return Ok(None);
+        }
+
+        let mut first = [0; 1];
+        if self.reader.read(&mut first)? == 0 {
+            return Ok(None);
+        }
+
+        let mut header = ChunkHeader::decode_new(io::Cursor::new(first).chain(&mut self.reader))?;
+        if self.byte_order == ByteOrder::BigEndian {
+            header = ChunkHeader::new(header.id(), header.get_le_size().swap_bytes());
+        }
+
+        self.remaining -= 8;
+        self.size = match (&self.ds64, header.get_le_size()) {
+            (Some(ds64), u32::MAX) => ds64.get_chunk_size(header.id()).unwrap_or(u32::MAX as u64),
+            (_, size) => size as u64,
+        };
+        // The pad byte of the last chunk may be left out of the RIFF size
+        if self.size > self.remaining {
+            self.remaining = 0;
+            return Err(Error::TruncatedChunk(header.id()));
+        }
+        self.pending = padded(self.size);
+        self.current = Some(header);
+
+        Ok(Some(header))
+    }
+
+    // Size of the current chunk body, without pad
+    pub fn body_size(&self) -> u64 {
+        self.size
+    }
+
+    // Reads the body of the current chunk, failing if the stream ends early
+    pub fn read_body(&mut self) -> Result<Vec<u8>, Error> {
+        let id = self.current_id();
+        let size = self.size;
+
+        let body = self.read_partial_body()?;
+        if (body.len() as u64) < size {
+            return Err(Error::TruncatedChunk(id));
+        }
+
+        Ok(body)
+    }
+
+    // Reads as much of the current chunk body as the stream holds
+    pub fn read_partial_body(&mut self) -> Result<Vec<u8>, Error> {
+        let size = self.size;
+
+        let body = read_partial(&mut self.reader, size)?;
+        self.consume(body.len() as u64);
+
+        // Pad byte, absent when the chunk is the last thing in a truncated file
+        if body.len() as u64 == size && self.pending > 0 {
+            let mut pad = [0; 1];
+            let read = self.reader.read(&mut pad)? as u64;
+            self.consume(read);
+        }
+        self.pending = 0;
+
+        Ok(body)
+    }
+
+    fn skip_body(&mut self) -> Result<(), Error> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+
+        let skipped = io::copy(&mut (&mut self.reader).take(self.pending), &mut io::sink())?;
+        self.consume(skipped);
+
+        if self.pending == 1 && self.size % 2 == 1 {
+            // Only the pad byte is missing, nothing left to walk anyway
+            self.remaining = 0;
+            self.pending = 0;
+        } else if self.pending > 0 {
+            let id = self.current_id();
+            self.pending = 0;
+            return Err(Error::TruncatedChunk(id));
+        }
+
+        Ok(())
+    }
+
+    fn consume(&mut self, bytes: u64) {
+        self.pending = self.pending.saturating_sub(bytes);
+        self.remaining = self.remaining.saturating_sub(bytes);
+    }
+
+    fn current_id(&self) -> FourCC {
+        self.current.map(|header| header.id()).unwrap_or_default()
+    }
+}
+
+impl<R: Read + Seek> ChunkReader<R> {
+    // Jumps over the current chunk body without reading it, handy for huge data chunks
+    pub fn seek_body(&mut self) -> Result<(), Error> {
+        let pending = self.pending;
+        self.reader.seek(SeekFrom::Current(pending as i64))?;
+        self.consume(pending);
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = Result<RawChunk, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = match self.next_header() {
+            Ok(Some(header)) => self
+                .read_body()
+                .map(|data| RawChunk::new(header.id(), data)),
+            Ok(None) => return None,
+            Err(err) => Err(err),
+        };
+
+        // Stop walking after the first error, the stream position is unknown
+        if chunk.is_err() {
+            self.remaining = 0;
+        }
+
+        Some(chunk)
+    }
+}
+
+// Size of the RIFF body taken by chunks, as written after the form type
+pub(crate) fn chunks_size(chunks: &[RawChunk]) -> u64 {
+    chunks
+        .iter()
+        .map(|chunk| 8 + padded(chunk.get_le_size() as u64))
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{codable::Encodable, header_ds64::test::oversized_junk, wav::Wav};
+
+    fn body() -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend(
+            RawChunk::new(FourCC::new(*b"JUNK"), vec![7; 3])
+                .encode()
+                .unwrap(),
+        );
+        body.extend(
+            RawChunk::new(FourCC::new(*b"LIST"), vec![1, 2])
+                .encode()
+                .unwrap(),
+        );
+        body
+    }
+
+    #[test]
+    fn walks_padded_chunks() {
+        let body = body();
+        let chunks = ChunkReader::new(&body[..], body.len() as u64)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].id(), FourCC::new(*b"JUNK"));
+        assert_eq!(chunks[0].data(), [7, 7, 7]);
+        assert_eq!(chunks[1].id(), FourCC::new(*b"LIST"));
+        assert_eq!(chunks[1].data(), [1, 2]);
+        assert_eq!(chunks_size(&chunks), body.len() as u64);
+    }
+
+    #[test]
+    fn skips_unread_bodies() {
+        let body = body();
+        let mut reader = ChunkReader::new(&body[..], body.len() as u64);
+
+        let first = reader.next_header().unwrap().unwrap();
+        let second = reader.next_header().unwrap().unwrap();
+
+        assert_eq!(first.id(), FourCC::new(*b"JUNK"));
+        assert_eq!(second.id(), FourCC::new(*b"LIST"));
+        assert!(reader.next_header().unwrap().is_none());
+    }
+
+    #[test]
+    fn reports_truncated_chunk() {
+        let body = body();
+        let mut reader = ChunkReader::new(&body[..6], body.len() as u64);
+
+        assert!(matches!(reader.next(), Some(Err(Error::TruncatedChunk(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn rejects_chunks_past_riff() {
+        let file = oversized_junk();
+        let (_, _, mut reader) = ChunkReader::open(&file[..]).unwrap();
+
+        assert!(matches!(
+            reader.next_header(),
+            Err(Error::TruncatedChunk(id)) if id == FourCC::new(*b"JUNK")
+        ));
+        assert!(reader.next().is_none());
+        assert!(Wav::<i16>::decode_new(&file[..]).is_err());
+    }
+}
@@ -1,11 +1,33 @@
+use crate::error::Error;
 use std::io::Read;
 
-pub trait Decodable {
-    fn decode_new<R: Read>(mutreader: R) -> Self;
+pub trait Decodable: Sized {
+    fn decode_new<R: Read>(reader: R) -> Result<Self, Error>;
 }
 
 pub trait Encodable {
-    fn encode(&self) -> Vec<u8>;
+    fn encode(&self) -> Result<Vec<u8>, Error>;
 }
 
 pub trait Codable: Decodable + Encodable {}
+
+impl<T: Decodable + Encodable> Codable for T {}
+
+// Fills the whole buffer, reporting an early EOF as a truncated `id` chunk
+pub(crate) fn read_exact<R: Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    id: &[u8; 4],
+) -> Result<(), Error> {
+    reader
+        .read_exact(buffer)
+        .map_err(|err| Error::from_read(err, id))
+}
+
+// Up to `size` bytes, fewer if the stream ends first. Sample data is read this way
+// in every container: a recording cut short still holds usable samples.
+pub(crate) fn read_partial<R: Read>(reader: R, size: u64) -> Result<Vec<u8>, Error> {
+    let mut body = Vec::new();
+    reader.take(size).read_to_end(&mut body)?;
+    Ok(body)
+}
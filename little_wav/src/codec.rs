@@ -0,0 +1,75 @@
+use crate::{
+    error::Error,
+    g711,
+    header_format::{Encoding, HeaderFormat},
+    sample::{decode_samples, encode_samples, Sample, SampleFormat},
+};
+
+// Float samples are only ever stored as IEEE, so plain LPCM resolves to the sample's own encoding
+pub(crate) fn resolve_encoding(encoding: Encoding, sample: SampleFormat) -> Encoding {
+    match encoding {
+        Encoding::LPCM => sample.get_encoding(),
+        _ => encoding,
+    }
+}
+
+// Bytes one `sample` takes on disk once stored with the given encoding
+pub(crate) fn stored_sample_size(encoding: Encoding, sample: SampleFormat) -> usize {
+    match encoding {
+        Encoding::ALAW | Encoding::MULAW => 1,
+        _ => sample.get_sample_size(),
+    }
+}
+
+// Turns in-memory samples into body bytes, companding them when the format asks for it
+pub(crate) fn encode_body<T: Sample>(
+    format: &HeaderFormat,
+    samples: &[T],
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    format.check_sample_format(T::FORMAT)?;
+
+    match format.get_encoding() {
+        Encoding::ALAW => compand(samples, out, g711::alaw_encode),
+        Encoding::MULAW => compand(samples, out, g711::mulaw_encode),
+        _ => encode_samples(samples, out),
+    }
+
+    Ok(())
+}
+
+pub(crate) fn decode_body<T: Sample>(format: &HeaderFormat, bytes: &[u8]) -> Result<Vec<T>, Error> {
+    format.check_sample_format(T::FORMAT)?;
+
+    Ok(match format.get_encoding() {
+        Encoding::ALAW => expand(bytes, g711::alaw_decode),
+        Encoding::MULAW => expand(bytes, g711::mulaw_decode),
+        _ => decode_samples(bytes),
+    })
+}
+
+// One sample out of its stored bytes, for readers that go sample by sample
+pub(crate) fn decode_sample<T: Sample>(encoding: Encoding, bytes: &[u8]) -> T {
+    match encoding {
+        Encoding::ALAW => T::from_le_bytes(&g711::alaw_decode(bytes[0]).to_le_bytes()),
+        Encoding::MULAW => T::from_le_bytes(&g711::mulaw_decode(bytes[0]).to_le_bytes()),
+        _ => T::from_le_bytes(bytes),
+    }
+}
+
+// Companded formats are always handled as linear i16 in memory, as checked above
+fn compand<T: Sample>(samples: &[T], out: &mut Vec<u8>, encode: fn(i16) -> u8) {
+    let mut linear = Vec::with_capacity(2);
+    for sample in samples {
+        linear.clear();
+        sample.write_le_bytes(&mut linear);
+        out.push(encode(i16::from_le_bytes([linear[0], linear[1]])));
+    }
+}
+
+fn expand<T: Sample>(bytes: &[u8], decode: fn(u8) -> i16) -> Vec<T> {
+    bytes
+        .iter()
+        .map(|byte| T::from_le_bytes(&decode(*byte).to_le_bytes()))
+        .collect()
+}
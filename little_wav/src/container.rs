@@ -0,0 +1,19 @@
+use crate::sample::ByteOrder;
+
+// File layout a Wav is read from and written to, the audio itself is the same in all of them
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Container {
+    // RIFF/WAVE, or its RF64/BW64 forms
+    #[default]
+    Wave,
+    // RIFF with big-endian sizes, fmt fields and samples, no 64-bit form
+    Rifx,
+    // Sony Wave64: GUID chunk ids, 64-bit sizes and 8-byte alignment
+    Wave64,
+    // Big-endian AIFF, written as AIFF-C when the encoding needs a compression type
+    Aiff,
+    // AIFF-C, integer samples go as "sowt" when little-endian
+    AiffC(ByteOrder),
+    // Sun/NeXT .au, big-endian with no room for other chunks
+    Au,
+}
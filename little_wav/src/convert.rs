@@ -0,0 +1,118 @@
+use crate::sample::Sample;
+
+// Noise added before rounding when samples lose resolution
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Dither {
+    #[default]
+    None,
+    // Triangular PDF noise spanning one least significant bit of the target each way
+    Triangular,
+}
+
+// Converts samples between formats through full scale f64, see Sample::to_f64
+pub fn convert_samples<S: Sample, D: Sample>(samples: &[S], dither: Dither) -> Vec<D> {
+    let source = S::FORMAT;
+    let target = D::FORMAT;
+
+    // Only worth it when an integer target drops bits the source had
+    let reduces = !target.is_float()
+        && (source.is_float() || source.get_bits_per_sample() > target.get_bits_per_sample());
+
+    match dither {
+        Dither::Triangular if reduces => {
+            let lsb = 1.0 / (1u64 << (target.get_bits_per_sample() - 1)) as f64;
+            let mut noise = Tpdf::new();
+
+            samples
+                .iter()
+                .map(|sample| D::from_f64(sample.to_f64() + noise.next() * lsb))
+                .collect()
+        }
+        _ => samples
+            .iter()
+            .map(|sample| D::from_f64(sample.to_f64()))
+            .collect(),
+    }
+}
+
+// xorshift64* based triangular noise, seeded the same way every time so output is reproducible
+struct Tpdf {
+    state: u64,
+}
+
+impl Tpdf {
+    fn new() -> Self {
+        Self {
+            state: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    // Uniform in 0.0..1.0
+    fn next_uniform(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let value = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Difference of two uniforms, triangular in -1.0..1.0
+    fn next(&mut self) -> f64 {
+        self.next_uniform() - self.next_uniform()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sample::I24;
+
+    #[test]
+    fn unsigned_8_bit_offset() {
+        let converted = convert_samples::<u8, i16>(&[0, 128, 255], Dither::None);
+        assert_eq!(converted, [i16::MIN, 0, 32_512]);
+
+        let converted = convert_samples::<i16, u8>(&[i16::MIN, 0, i16::MAX], Dither::None);
+        assert_eq!(converted, [0, 128, 255]);
+    }
+
+    #[test]
+    fn clips_both_ends() {
+        let converted = convert_samples::<f32, i16>(&[-2.0, -1.0, 1.0, 2.0], Dither::None);
+        assert_eq!(converted, [i16::MIN, i16::MIN, i16::MAX, i16::MAX]);
+
+        let converted = convert_samples::<f64, I24>(&[1.5, -1.5], Dither::None);
+        assert_eq!(converted, [I24::MAX, I24::MIN]);
+    }
+
+    #[test]
+    fn widening_is_lossless() {
+        let samples = [i16::MIN, -1, 0, 1, i16::MAX];
+
+        let wide = convert_samples::<i16, i32>(&samples, Dither::Triangular);
+        assert_eq!(wide[4], (i16::MAX as i32) << 16);
+        assert_eq!(convert_samples::<i32, i16>(&wide, Dither::None), samples);
+
+        let float = convert_samples::<i16, f32>(&samples, Dither::Triangular);
+        assert_eq!(float[0], -1.0);
+        assert_eq!(convert_samples::<f32, i16>(&float, Dither::None), samples);
+    }
+
+    #[test]
+    fn dither_stays_within_one_bit() {
+        let samples: Vec<f64> = (0..1_000).map(|i| (i as f64 / 100.0).sin() * 0.5).collect();
+
+        let plain = convert_samples::<f64, i16>(&samples, Dither::None);
+        let dithered = convert_samples::<f64, i16>(&samples, Dither::Triangular);
+
+        assert_ne!(plain, dithered);
+        for (plain, dithered) in plain.iter().zip(&dithered) {
+            assert!((*plain as i32 - *dithered as i32).abs() <= 1);
+        }
+        assert_eq!(
+            dithered,
+            convert_samples::<f64, i16>(&samples, Dither::Triangular)
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    chunk::{padded, push_chunk, read_text, RawChunk},
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+    list_info::LIST_TYPE,
+    sample::ByteOrder,
+};
+use std::ops::Range;
+
+pub(crate) const CUE_TYPE: &[u8; 4] = b"cue ";
+pub(crate) const ADTL_TYPE: &[u8; 4] = b"adtl";
+const LABL_TYPE: &[u8; 4] = b"labl";
+const NOTE_TYPE: &[u8; 4] = b"note";
+pub(crate) const LTXT_TYPE: &[u8; 4] = b"ltxt";
+// ltxt purpose of a region
+const RGN_PURPOSE: &[u8; 4] = b"rgn ";
+
+// A cue point, named and described through LIST/adtl. A region when it has a length.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Marker {
+    // Cue point id, smpl loops refer to it
+    pub id: u32,
+    // First frame
+    pub position: u32,
+    // Frames covered, 0 for a plain marker
+    pub length: u32,
+    pub label: Option<String>,
+    pub note: Option<String>,
+}
+
+impl Marker {
+    pub fn new(id: u32, position: u32) -> Self {
+        Self {
+            id,
+            position,
+            ..Self::default()
+        }
+    }
+    pub fn region(id: u32, position: u32, length: u32) -> Self {
+        Self {
+            id,
+            position,
+            length,
+            ..Self::default()
+        }
+    }
+    pub fn is_region(&self) -> bool {
+        self.length > 0
+    }
+
+    // Where the marker ends up once `removed` frames are cut out, None if it goes with them
+    pub(crate) fn cut(&self, removed: &Range<u32>) -> Option<Self> {
+        let count = removed.end - removed.start;
+        let end = self.position.saturating_add(self.length);
+        let mut marker = self.clone();
+
+        if self.position >= removed.end {
+            marker.position -= count;
+        } else if self.position >= removed.start {
+            // Starts inside the cut, what's left of a region starts where the cut was
+            if end <= removed.end {
+                return None;
+            }
+            marker.position = removed.start;
+            marker.length = end - removed.end;
+        } else if end > removed.start {
+            marker.length -= end.min(removed.end) - removed.start;
+        }
+
+        Some(marker)
+    }
+}
+
+pub(crate) fn is_cue(chunk: &RawChunk) -> bool {
+    chunk.id() == FourCC::new(*CUE_TYPE)
+}
+pub(crate) fn is_adtl(chunk: &RawChunk) -> bool {
+    chunk.id() == FourCC::new(*LIST_TYPE) && chunk.data().get(..4) == Some(ADTL_TYPE)
+}
+
+// Cue points joined with their labels, notes and region lengths
+pub(crate) fn decode_markers(
+    cue: &RawChunk,
+    adtl: Option<&RawChunk>,
+) -> Result<Vec<Marker>, Error> {
+    let data = cue.data();
+    let truncated = || Error::TruncatedChunk(FourCC::new(*CUE_TYPE));
+
+    let count = u32_at(data, 0).ok_or_else(truncated)? as usize;
+    let mut markers = Vec::with_capacity(count.min(data.len() / 24));
+    for point in 0..count {
+        let at = 4 + point * 24;
+        let (id, position) = u32_at(data, at)
+            .zip(u32_at(data, at + 20))
+            .ok_or_else(truncated)?;
+        markers.push(Marker::new(id, position));
+    }
+
+    for (id, body) in adtl.map(adtl_entries).transpose()?.unwrap_or_default() {
+        let Some(marker) = u32_at(body, 0)
+            .and_then(|cue_id| markers.iter_mut().find(|marker| marker.id == cue_id))
+        else {
+            continue;
+        };
+        match id {
+            LABL_TYPE => marker.label = Some(read_text(&body[4..])),
+            NOTE_TYPE => marker.note = Some(read_text(&body[4..])),
+            LTXT_TYPE => marker.length = u32_at(body, 4).unwrap_or_default(),
+            _ => {}
+        }
+    }
+
+    Ok(markers)
+}
+
+// A sub-chunk of LIST/adtl, id and body
+type AdtlEntry<'a> = (&'a [u8; 4], &'a [u8]);
+
+fn adtl_entries(adtl: &RawChunk) -> Result<Vec<AdtlEntry<'_>>, Error> {
+    let data = adtl.data();
+    let mut entries = Vec::new();
+    let mut at = 4;
+    while let Some(size) = u32_at(data, at + 4) {
+        let id = data[at..at + 4].try_into().expect("4 bytes");
+        let body = data
+            .get(at + 8..)
+            .and_then(|rest| rest.get(..size as usize))
+            .ok_or(Error::TruncatedChunk(FourCC::new(*LIST_TYPE)))?;
+        at += 8 + padded(size as u64) as usize;
+        entries.push((id, body));
+    }
+    Ok(entries)
+}
+
+// A cue chunk, and a LIST/adtl one if any marker has a label, a note or a length.
+// Entries of the current adtl are carried over, except the labels, notes and lengths
+// of the markers being written or `replaced`.
+pub(crate) fn encode_markers(
+    markers: &[Marker],
+    replaced: &[Marker],
+    current: Option<&RawChunk>,
+) -> Result<(RawChunk, Option<RawChunk>), Error> {
+    let mut cue = Vec::with_capacity(4 + markers.len() * 24);
+    cue.extend_from_slice(&(markers.len() as u32).to_le_bytes());
+    for marker in markers {
+        cue.extend_from_slice(&marker.id.to_le_bytes());
+        cue.extend_from_slice(&marker.position.to_le_bytes());
+        cue.extend_from_slice(DATA_TYPE);
+        // Chunk and block start, 0 for a plain data chunk
+        cue.extend_from_slice(&[0; 8]);
+        cue.extend_from_slice(&marker.position.to_le_bytes());
+    }
+
+    let mut adtl = ADTL_TYPE.to_vec();
+    for (id, body) in current.map(adtl_entries).transpose()?.unwrap_or_default() {
+        let managed = matches!(id, LABL_TYPE | NOTE_TYPE | LTXT_TYPE)
+            && u32_at(body, 0).is_some_and(|cue_id| {
+                markers
+                    .iter()
+                    .chain(replaced)
+                    .any(|marker| marker.id == cue_id)
+            });
+        if !managed {
+            push_chunk(&mut adtl, id, body, ByteOrder::LittleEndian);
+        }
+    }
+
+    for marker in markers {
+        if marker.is_region() {
+            let mut ltxt = marker.id.to_le_bytes().to_vec();
+            ltxt.extend_from_slice(&marker.length.to_le_bytes());
+            ltxt.extend_from_slice(RGN_PURPOSE);
+            // Country, language, dialect and code page
+            ltxt.extend_from_slice(&[0; 8]);
+            push_chunk(&mut adtl, LTXT_TYPE, &ltxt, ByteOrder::LittleEndian);
+        }
+        for (id, text) in [(LABL_TYPE, &marker.label), (NOTE_TYPE, &marker.note)] {
+            if let Some(text) = text {
+                let mut body = marker.id.to_le_bytes().to_vec();
+                body.extend_from_slice(text.as_bytes());
+                body.push(0);
+                push_chunk(&mut adtl, id, &body, ByteOrder::LittleEndian);
+            }
+        }
+    }
+
+    let cue = RawChunk::new(FourCC::new(*CUE_TYPE), cue);
+    let adtl = (adtl.len() > 4).then(|| RawChunk::new(FourCC::new(*LIST_TYPE), adtl));
+
+    Ok((cue, adtl))
+}
+
+pub(crate) fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    let field = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes(field.try_into().expect("4 bytes")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut verse = Marker::region(2, 1_000, 500);
+        verse.label = Some("Verse".to_string());
+        verse.note = Some("second take".to_string());
+        let markers = vec![Marker::new(1, 10), verse];
+
+        let (cue, adtl) = encode_markers(&markers, &[], None).unwrap();
+        assert_eq!(cue.data().len(), 4 + 2 * 24);
+        assert_eq!(cue.data()[12..16], *DATA_TYPE);
+        let adtl = adtl.unwrap();
+        assert_eq!(adtl.data()[..8], *b"adtlltxt");
+
+        assert_eq!(decode_markers(&cue, Some(&adtl)).unwrap(), markers);
+
+        let (_, adtl) = encode_markers(&[Marker::new(1, 10)], &[], None).unwrap();
+        assert!(adtl.is_none());
+    }
+
+    #[test]
+    fn keeps_foreign_adtl_entries() {
+        let mut old = Marker::new(1, 10);
+        old.label = Some("Old".to_string());
+        let (_, adtl) = encode_markers(&[old.clone()], &[], None).unwrap();
+        let (_, data) = adtl.unwrap().into_parts();
+        let mut data = data;
+        // A file entry, and a label for a cue point nobody knows of
+        push_chunk(
+            &mut data,
+            b"file",
+            &[1, 0, 0, 0, 9],
+            ByteOrder::LittleEndian,
+        );
+        push_chunk(
+            &mut data,
+            LABL_TYPE,
+            b"\x07\0\0\0Lost\0",
+            ByteOrder::LittleEndian,
+        );
+        let adtl = RawChunk::new(FourCC::new(*LIST_TYPE), data);
+
+        let mut new = Marker::new(2, 20);
+        new.label = Some("New".to_string());
+        let (cue, adtl) = encode_markers(&[new.clone()], &[old], Some(&adtl)).unwrap();
+        let adtl = adtl.unwrap();
+        let entries = adtl_entries(&adtl).unwrap();
+        let ids: Vec<_> = entries.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [b"file", LABL_TYPE, LABL_TYPE]);
+        assert_eq!(entries[1].1, b"\x07\0\0\0Lost\0");
+        assert_eq!(decode_markers(&cue, Some(&adtl)).unwrap(), [new]);
+
+        let broken = RawChunk::new(FourCC::new(*LIST_TYPE), b"adtlfile\x09\0\0\0".to_vec());
+        assert!(encode_markers(&[], &[], Some(&broken)).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_cue() {
+        let (cue, _) = encode_markers(&[Marker::new(1, 10)], &[], None).unwrap();
+        let (_, data) = cue.into_parts();
+        let cue = RawChunk::new(FourCC::new(*CUE_TYPE), data[..20].to_vec());
+
+        assert!(matches!(
+            decode_markers(&cue, None),
+            Err(Error::TruncatedChunk(_))
+        ));
+    }
+
+    #[test]
+    fn cuts_markers() {
+        let removed = 100..200;
+
+        assert_eq!(Marker::new(1, 50).cut(&removed), Some(Marker::new(1, 50)));
+        assert_eq!(Marker::new(1, 150).cut(&removed), None);
+        assert_eq!(Marker::new(1, 250).cut(&removed), Some(Marker::new(1, 150)));
+        // Regions lose what overlaps the cut
+        assert_eq!(
+            Marker::region(1, 50, 100).cut(&removed),
+            Some(Marker::region(1, 50, 50))
+        );
+        assert_eq!(
+            Marker::region(1, 150, 100).cut(&removed),
+            Some(Marker::region(1, 100, 50))
+        );
+        assert_eq!(
+            Marker::region(1, 50, 300).cut(&removed),
+            Some(Marker::region(1, 50, 200))
+        );
+        assert_eq!(Marker::region(1, 120, 50).cut(&removed), None);
+    }
+}
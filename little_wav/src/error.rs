@@ -0,0 +1,160 @@
+use crate::{four_cc::FourCC, guid::Guid, header_format::Encoding, sample::SampleFormat};
+use std::{fmt, io};
+
+// Everything that can go wrong while decoding or encoding a WAV file
+#[derive(Debug)]
+pub enum Error {
+    // A chunk ended before all of its fields could be read
+    TruncatedChunk(FourCC),
+    // A chunk or form type did not hold the expected tag
+    BadMagic {
+        expected: FourCC,
+        found: FourCC,
+    },
+    // The fmt chunk declares an encoding tag we do not know
+    UnsupportedFormat(u16),
+    // A size field disagrees with the data it describes
+    SizeMismatch {
+        id: FourCC,
+        expected: u64,
+        found: u64,
+    },
+    // Fields of the fmt chunk contradict each other
+    InvalidFormat {
+        field: &'static str,
+        expected: u64,
+        found: u64,
+    },
+    // AIFF-C compression type we can't decode
+    UnsupportedCompression(FourCC),
+    // Sun .au encoding code we can't decode
+    UnsupportedAuEncoding(u32),
+    // Encoding and bit depth don't add up to a sample format we handle
+    UnsupportedSampleFormat {
+        encoding: Encoding,
+        bits_per_sample: u16,
+    },
+    // The sample type asked for doesn't match the sample format of the file
+    IncompatibleSample {
+        file: SampleFormat,
+        sample: SampleFormat,
+    },
+    // The sample count is not a whole number of frames
+    PartialFrame {
+        samples: u64,
+        channels: u16,
+    },
+    // Planar channel buffers of unequal length
+    ChannelLength {
+        channel: usize,
+        expected: u64,
+        found: u64,
+    },
+    // A channel index past the channel count
+    NoSuchChannel {
+        channel: usize,
+        channels: u16,
+    },
+    // A chunk required to make sense of the file is absent
+    MissingChunk(FourCC),
+    // A Wave64 chunk named by a GUID with no FourCC equivalent can't be written to RIFF
+    GuidChunk(Guid),
+    // A FourCC was built from a string that is not 4 bytes long
+    InvalidFourCC(String),
+    Io(io::Error),
+}
+
+impl Error {
+    // Maps an I/O error hit while reading chunk `id`, so that early EOF reads as truncation
+    pub(crate) fn from_read(err: io::Error, id: &[u8; 4]) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::TruncatedChunk(FourCC::new(*id)),
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TruncatedChunk(id) => write!(f, "chunk '{}' is truncated", id),
+            Error::BadMagic { expected, found } => {
+                write!(f, "expected '{}' but found '{}'", expected, found)
+            }
+            Error::UnsupportedFormat(tag) => write!(f, "unsupported format tag {:#06x}", tag),
+            Error::SizeMismatch {
+                id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "chunk '{}' size mismatch: expected {} bytes, found {}",
+                id, expected, found
+            ),
+            Error::InvalidFormat {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "invalid fmt chunk: {} should be {} but is {}",
+                field, expected, found
+            ),
+            Error::UnsupportedCompression(id) => {
+                write!(f, "unsupported AIFF-C compression '{}'", id)
+            }
+            Error::UnsupportedAuEncoding(code) => write!(f, "unsupported .au encoding {}", code),
+            Error::UnsupportedSampleFormat {
+                encoding,
+                bits_per_sample,
+            } => write!(
+                f,
+                "unsupported {}-bit {:?} samples",
+                bits_per_sample, encoding
+            ),
+            Error::IncompatibleSample { file, sample } => {
+                write!(f, "{:?} samples can't be handled as {:?}", file, sample)
+            }
+            Error::PartialFrame { samples, channels } => write!(
+                f,
+                "{} samples don't make whole frames of {} channels",
+                samples, channels
+            ),
+            Error::ChannelLength {
+                channel,
+                expected,
+                found,
+            } => write!(
+                f,
+                "channel {} holds {} samples, expected {}",
+                channel, found, expected
+            ),
+            Error::NoSuchChannel { channel, channels } => {
+                write!(f, "no channel {} out of {}", channel, channels)
+            }
+            Error::MissingChunk(id) => write!(f, "chunk '{}' not found", id),
+            Error::GuidChunk(guid) => {
+                write!(f, "chunk {} has no FourCC to be written as RIFF", guid)
+            }
+            Error::InvalidFourCC(string) => {
+                write!(f, "FourCC must be 4 characters long, got {:?}", string)
+            }
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -1,15 +1,18 @@
-#[derive(Default, Debug)]
+use crate::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
 pub struct FourCC {
     value: [u8; 4],
 }
 
 impl FourCC {
-    pub fn new(value: [u8; 4]) -> Result<Self, ()> {
-        Ok(Self { value })
+    pub const fn new(value: [u8; 4]) -> Self {
+        Self { value }
     }
-    pub fn from(string: &str) -> Result<Self, &str> {
+    pub fn from(string: &str) -> Result<Self, Error> {
         if string.len() != 4 {
-            return Err("FourCC must be 4 characters long");
+            return Err(Error::InvalidFourCC(string.to_string()));
         }
 
         let bytes = string.as_bytes();
@@ -21,6 +24,15 @@ impl FourCC {
     pub fn as_bytes(&self) -> [u8; 4] {
         self.value
     }
+    pub fn as_array(&self) -> &[u8; 4] {
+        &self.value
+    }
+}
+
+impl fmt::Display for FourCC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.value))
+    }
 }
 
 #[cfg(test)]
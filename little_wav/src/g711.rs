@@ -0,0 +1,120 @@
+// G.711 companding between 16-bit linear PCM and 8-bit A-law / mu-law,
+// after the reference implementation by Sun Microsystems
+
+const MULAW_BIAS: i32 = 0x84;
+const MULAW_CLIP: i32 = 32_635;
+
+// Upper bounds of the A-law segments, on 13-bit magnitudes
+const ALAW_SEGMENT_END: [i32; 8] = [0x1f, 0x3f, 0x7f, 0xff, 0x1ff, 0x3ff, 0x7ff, 0xfff];
+
+pub fn alaw_encode(sample: i16) -> u8 {
+    let mut magnitude = (sample as i32) >> 3;
+    let mask = if magnitude >= 0 {
+        0xd5
+    } else {
+        magnitude = -magnitude - 1;
+        0x55
+    };
+
+    let segment = ALAW_SEGMENT_END
+        .iter()
+        .position(|end| magnitude <= *end)
+        .unwrap_or(8);
+    if segment >= 8 {
+        return (0x7f ^ mask) as u8;
+    }
+
+    let shift = if segment < 2 { 1 } else { segment };
+    let value = ((segment as i32) << 4) | ((magnitude >> shift) & 0x0f);
+
+    (value ^ mask) as u8
+}
+
+pub fn alaw_decode(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+
+    let mut magnitude = ((byte & 0x0f) as i32) << 4;
+    let segment = ((byte & 0x70) >> 4) as i32;
+    match segment {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        _ => magnitude = (magnitude + 0x108) << (segment - 1),
+    }
+
+    if byte & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+pub fn mulaw_encode(sample: i16) -> u8 {
+    let mut magnitude = sample as i32;
+    let sign = if magnitude < 0 {
+        magnitude = -magnitude;
+        0x80
+    } else {
+        0
+    };
+    magnitude = magnitude.min(MULAW_CLIP) + MULAW_BIAS;
+
+    // Position of the highest set bit among bits 7 to 14
+    let mut exponent = 7;
+    while exponent > 0 && magnitude & (0x80 << exponent) == 0 {
+        exponent -= 1;
+    }
+    let mantissa = (magnitude >> (exponent + 3)) & 0x0f;
+
+    !(sign | (exponent << 4) | mantissa) as u8
+}
+
+pub fn mulaw_decode(byte: u8) -> i16 {
+    let byte = !byte;
+
+    let exponent = ((byte & 0x70) >> 4) as i32;
+    let mantissa = (byte & 0x0f) as i32;
+    let magnitude = (((mantissa << 3) + MULAW_BIAS) << exponent) - MULAW_BIAS;
+
+    if byte & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn alaw_known_values() {
+        assert_eq!(alaw_encode(0), 0xd5);
+        assert_eq!(alaw_decode(0xd5), 8);
+        assert_eq!(alaw_encode(i16::MAX), 0xaa);
+        assert_eq!(alaw_encode(i16::MIN), 0x2a);
+        assert_eq!(alaw_decode(0xaa), 32_256);
+    }
+
+    #[test]
+    fn mulaw_known_values() {
+        assert_eq!(mulaw_encode(0), 0xff);
+        assert_eq!(mulaw_decode(0xff), 0);
+        assert_eq!(mulaw_encode(i16::MAX), 0x80);
+        assert_eq!(mulaw_encode(i16::MIN), 0x00);
+        assert_eq!(mulaw_decode(0x80), 32_124);
+    }
+
+    #[test]
+    fn decoded_values_are_stable() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(
+                alaw_decode(alaw_encode(alaw_decode(byte))),
+                alaw_decode(byte)
+            );
+            assert_eq!(
+                mulaw_decode(mulaw_encode(mulaw_decode(byte))),
+                mulaw_decode(byte)
+            );
+        }
+    }
+}
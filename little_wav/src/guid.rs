@@ -0,0 +1,114 @@
+use crate::four_cc::FourCC;
+use std::fmt;
+
+// Tail shared by every KSDATAFORMAT_SUBTYPE_* GUID, the format tag goes in front
+const SUBTYPE_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+// Tail of the Wave64 chunk GUIDs built out of a FourCC, e.g. 20746d66-acf3-11d3-8cd1-00c04f8edb8a for "fmt "
+const FOUR_CC_TAIL: [u8; 12] = [
+    0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+
+// 16 byte GUID, kept in its on-disk layout (first three fields little-endian)
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+pub struct Guid {
+    value: [u8; 16],
+}
+
+impl Guid {
+    pub const fn new(value: [u8; 16]) -> Self {
+        Self { value }
+    }
+    pub fn as_bytes(&self) -> [u8; 16] {
+        self.value
+    }
+    pub fn as_array(&self) -> &[u8; 16] {
+        &self.value
+    }
+
+    // Sub format GUID of WAVE_FORMAT_EXTENSIBLE for a plain format tag
+    pub fn from_format_tag(tag: u16) -> Self {
+        let mut value = [0; 16];
+        value[..2].copy_from_slice(&tag.to_le_bytes());
+        value[2..].copy_from_slice(&SUBTYPE_TAIL);
+
+        Self { value }
+    }
+    pub fn get_format_tag(&self) -> Option<u16> {
+        if self.value[2..] != SUBTYPE_TAIL {
+            return None;
+        }
+
+        Some(u16::from_le_bytes([self.value[0], self.value[1]]))
+    }
+
+    // Wave64 id of a chunk known by its FourCC in RIFF
+    pub const fn from_four_cc(id: [u8; 4]) -> Self {
+        let mut value = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            value[i] = if i < 4 { id[i] } else { FOUR_CC_TAIL[i - 4] };
+            i += 1;
+        }
+
+        Self { value }
+    }
+    pub fn get_four_cc(&self) -> Option<FourCC> {
+        if self.value[4..] != FOUR_CC_TAIL {
+            return None;
+        }
+
+        Some(FourCC::new([
+            self.value[0],
+            self.value[1],
+            self.value[2],
+            self.value[3],
+        ]))
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = &self.value;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-",
+            u32::from_le_bytes([v[0], v[1], v[2], v[3]]),
+            u16::from_le_bytes([v[4], v[5]]),
+            u16::from_le_bytes([v[6], v[7]])
+        )?;
+        for (i, byte) in v[8..].iter().enumerate() {
+            if i == 2 {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pcm_sub_format() {
+        let guid = Guid::from_format_tag(1);
+
+        assert_eq!(guid.to_string(), "00000001-0000-0010-8000-00aa00389b71");
+        assert_eq!(guid.get_format_tag(), Some(1));
+        assert_eq!(Guid::new([1; 16]).get_format_tag(), None);
+    }
+
+    #[test]
+    fn four_cc_chunk_id() {
+        let guid = Guid::from_four_cc(*b"fmt ");
+
+        assert_eq!(guid.to_string(), "20746d66-acf3-11d3-8cd1-00c04f8edb8a");
+        assert_eq!(guid.get_four_cc(), Some(FourCC::new(*b"fmt ")));
+        assert_eq!(Guid::from_format_tag(1).get_four_cc(), None);
+    }
+}
@@ -1,24 +1,39 @@
 use crate::{
     chunk::Chunk,
-    codable::{Decodable, Encodable},
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
     four_cc::FourCC,
 };
 use std::io::Read;
 
-const DATA_TYPE: &[u8; 4] = b"data";
+pub(crate) const DATA_TYPE: &[u8; 4] = b"data";
 
-#[derive(Default, Debug)]
+#[derive(Clone, Copy, Default, Debug)]
 pub struct HeaderData {
-    size: u32,
+    size: u64,
+    // Size lives in ds64, 0xFFFFFFFF is written in its place
+    wide: bool,
 }
 
 impl HeaderData {
     pub fn new() -> Self {
-        Self { size: 0 }
+        Self {
+            size: 0,
+            wide: false,
+        }
     }
 
     pub fn set_size(&mut self, samples_len: usize, sample_size: usize) {
-        self.size = sample_size as u32 * samples_len as u32;
+        self.size = sample_size as u64 * samples_len as u64;
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub(crate) fn resize(&mut self, size: u64, wide: bool) {
+        self.size = size;
+        self.wide = wide;
     }
 }
 
@@ -28,31 +43,40 @@ impl Chunk for HeaderData {
     }
 
     fn get_le_size(&self) -> u32 {
-        self.size
+        if self.wide {
+            u32::MAX
+        } else {
+            self.size as u32
+        }
     }
 }
 
 impl Encodable for HeaderData {
-    fn encode(&self) -> Vec<u8> {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
         let mut vec = Vec::new();
 
         vec.extend_from_slice(self.get_be_id());
-        vec.extend_from_slice(&self.size.to_le_bytes());
+        vec.extend_from_slice(&self.get_le_size().to_le_bytes());
 
-        vec
+        Ok(vec)
     }
 }
 
 impl Decodable for HeaderData {
-    fn decode_new<R: Read>(mut reader: R) -> Self {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
         let mut buffer_16 = [0; 4];
 
-        reader.read_exact(&mut buffer_16).unwrap();
-        let _id = FourCC::new(buffer_16).unwrap();
+        read_exact(&mut reader, &mut buffer_16, DATA_TYPE)?;
+        if buffer_16 != *DATA_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*DATA_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
 
-        reader.read_exact(&mut buffer_16).unwrap();
-        let size = u32::from_le_bytes(buffer_16);
+        read_exact(&mut reader, &mut buffer_16, DATA_TYPE)?;
+        let size = u32::from_le_bytes(buffer_16) as u64;
 
-        Self { size }
+        Ok(Self { size, wide: false })
     }
 }
@@ -0,0 +1,165 @@
+use crate::{
+    chunk::Chunk,
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+};
+use std::io::Read;
+
+pub(crate) const DS64_TYPE: &[u8; 4] = b"ds64";
+// Placeholder the size of a bare ds64, so a RIFF file can be turned into RF64 in place
+pub(crate) const JUNK_TYPE: &[u8; 4] = b"JUNK";
+
+// 64-bit sizes of RF64 and BW64 files, for every field left at 0xFFFFFFFF
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct HeaderDs64 {
+    riff_size: u64,
+    data_size: u64,
+    sample_count: u64,
+    // Sizes of other chunks too big for their header
+    table: Vec<(FourCC, u64)>,
+}
+
+impl HeaderDs64 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_sizes(&mut self, riff_size: u64, data_size: u64, sample_count: u64) {
+        self.riff_size = riff_size;
+        self.data_size = data_size;
+        self.sample_count = sample_count;
+    }
+
+    pub fn get_riff_size(&self) -> u64 {
+        self.riff_size
+    }
+    pub(crate) fn get_sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    // Real size of a chunk whose header says 0xFFFFFFFF
+    pub(crate) fn get_chunk_size(&self, id: FourCC) -> Option<u64> {
+        match id.as_array() {
+            DATA_TYPE => Some(self.data_size),
+            _ => self
+                .table
+                .iter()
+                .find(|(table_id, _)| *table_id == id)
+                .map(|(_, size)| *size),
+        }
+    }
+}
+
+impl Chunk for HeaderDs64 {
+    fn get_be_id(&self) -> &[u8; 4] {
+        DS64_TYPE
+    }
+
+    fn get_le_size(&self) -> u32 {
+        28 + 12 * self.table.len() as u32
+    }
+}
+
+impl Encodable for HeaderDs64 {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut vec = Vec::new();
+
+        vec.extend_from_slice(self.get_be_id());
+        vec.extend_from_slice(&self.get_le_size().to_le_bytes());
+        vec.extend_from_slice(&self.riff_size.to_le_bytes());
+        vec.extend_from_slice(&self.data_size.to_le_bytes());
+        vec.extend_from_slice(&self.sample_count.to_le_bytes());
+        vec.extend_from_slice(&(self.table.len() as u32).to_le_bytes());
+        for (id, size) in &self.table {
+            vec.extend_from_slice(id.as_array());
+            vec.extend_from_slice(&size.to_le_bytes());
+        }
+
+        Ok(vec)
+    }
+}
+
+impl Decodable for HeaderDs64 {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut buffer_16 = [0; 4];
+        let mut buffer_64 = [0; 8];
+
+        read_exact(&mut reader, &mut buffer_16, DS64_TYPE)?;
+        if buffer_16 != *DS64_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*DS64_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
+
+        read_exact(&mut reader, &mut buffer_16, DS64_TYPE)?;
+        let size = u32::from_le_bytes(buffer_16);
+        if size < 28 {
+            return Err(Error::SizeMismatch {
+                id: FourCC::new(*DS64_TYPE),
+                expected: 28,
+                found: size as u64,
+            });
+        }
+
+        read_exact(&mut reader, &mut buffer_64, DS64_TYPE)?;
+        let riff_size = u64::from_le_bytes(buffer_64);
+        read_exact(&mut reader, &mut buffer_64, DS64_TYPE)?;
+        let data_size = u64::from_le_bytes(buffer_64);
+        read_exact(&mut reader, &mut buffer_64, DS64_TYPE)?;
+        let sample_count = u64::from_le_bytes(buffer_64);
+
+        read_exact(&mut reader, &mut buffer_16, DS64_TYPE)?;
+        // Never trust the table length beyond what the chunk can hold
+        let table_length = u32::from_le_bytes(buffer_16).min((size - 28) / 12);
+
+        let mut table = Vec::new();
+        for _ in 0..table_length {
+            read_exact(&mut reader, &mut buffer_16, DS64_TYPE)?;
+            read_exact(&mut reader, &mut buffer_64, DS64_TYPE)?;
+            table.push((FourCC::new(buffer_16), u64::from_le_bytes(buffer_64)));
+        }
+
+        Ok(Self {
+            riff_size,
+            data_size,
+            sample_count,
+            table,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    // RF64 file whose ds64 table gives its JUNK chunk a size of u64::MAX
+    pub(crate) fn oversized_junk() -> Vec<u8> {
+        let mut ds64 = HeaderDs64::new();
+        ds64.set_sizes(u64::MAX, 0, 0);
+        ds64.table.push((FourCC::new(*b"JUNK"), u64::MAX));
+
+        let mut file = b"RF64\xff\xff\xff\xffWAVE".to_vec();
+        file.extend(ds64.encode().unwrap());
+        file.extend_from_slice(b"JUNK\xff\xff\xff\xff");
+        file.extend_from_slice(&[0; 16]);
+        file
+    }
+
+    #[test]
+    fn round_trip_with_table() {
+        let mut ds64 = HeaderDs64::new();
+        ds64.set_sizes(5 << 32, 4 << 32, 1 << 32);
+        ds64.table.push((FourCC::new(*b"axml"), 6 << 32));
+
+        let encoded = ds64.encode().unwrap();
+        assert_eq!(encoded.len(), 8 + 40);
+
+        let decoded = HeaderDs64::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded, ds64);
+        assert_eq!(decoded.get_chunk_size(FourCC::new(*b"axml")), Some(6 << 32));
+        assert_eq!(decoded.get_chunk_size(FourCC::new(*b"data")), Some(4 << 32));
+    }
+}
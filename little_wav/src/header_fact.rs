@@ -0,0 +1,78 @@
+use crate::{
+    chunk::Chunk,
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
+};
+use std::io::Read;
+
+pub(crate) const FACT_TYPE: &[u8; 4] = b"fact";
+
+// Every non-PCM file carries the number of sample frames in a fact chunk
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct HeaderFact {
+    sample_length: u32,
+}
+
+impl HeaderFact {
+    pub fn new() -> Self {
+        Self { sample_length: 0 }
+    }
+
+    // Past u32 the count is 0xFFFFFFFF and the real one goes in ds64
+    pub fn set_size(&mut self, samples_len: usize, channels: usize) {
+        let frames = samples_len / channels.max(1);
+        self.sample_length = u32::try_from(frames).unwrap_or(u32::MAX);
+    }
+}
+
+impl Chunk for HeaderFact {
+    fn get_be_id(&self) -> &[u8; 4] {
+        FACT_TYPE
+    }
+
+    fn get_le_size(&self) -> u32 {
+        4
+    }
+}
+
+impl Encodable for HeaderFact {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut vec = Vec::new();
+
+        vec.extend_from_slice(self.get_be_id());
+        vec.extend_from_slice(&self.get_le_size().to_le_bytes());
+        vec.extend_from_slice(&self.sample_length.to_le_bytes());
+
+        Ok(vec)
+    }
+}
+
+impl Decodable for HeaderFact {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut buffer_16 = [0; 4];
+
+        read_exact(&mut reader, &mut buffer_16, FACT_TYPE)?;
+        if buffer_16 != *FACT_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*FACT_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
+
+        read_exact(&mut reader, &mut buffer_16, FACT_TYPE)?;
+        let size = u32::from_le_bytes(buffer_16);
+        if size < 4 {
+            return Err(Error::SizeMismatch {
+                id: FourCC::new(*FACT_TYPE),
+                expected: 4,
+                found: size as u64,
+            });
+        }
+
+        read_exact(&mut reader, &mut buffer_16, FACT_TYPE)?;
+        let sample_length = u32::from_le_bytes(buffer_16);
+
+        Ok(Self { sample_length })
+    }
+}
@@ -1,13 +1,22 @@
 use crate::{
+    channel_mask::ChannelMask,
     chunk::Chunk,
-    codable::{Decodable, Encodable},
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
     four_cc::FourCC,
+    guid::Guid,
+    sample::SampleFormat,
 };
-use std::io::Read;
+use std::io::{self, Read};
 
-const FMT_TYPE: &[u8; 4] = b"fmt ";
+pub(crate) const FMT_TYPE: &[u8; 4] = b"fmt ";
 
-#[derive(Clone, Copy, Default, Debug)]
+const EXTENSIBLE_TAG: [u8; 2] = [0xfe, 0xff];
+// cbSize of WAVE_FORMAT_EXTENSIBLE: valid bits, channel mask and sub format GUID
+const EXTENSION_SIZE: u16 = 22;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub enum Encoding {
     #[default]
     LPCM = 1,
@@ -26,24 +35,37 @@ impl Encoding {
         }
     }
 
-    fn from_le_bytes(bytes: [u8; 2]) -> Self {
+    fn from_le_bytes(bytes: [u8; 2]) -> Result<Self, Error> {
         match bytes {
-            [1, 0] => Encoding::LPCM,
-            [3, 0] => Encoding::IEEE,
-            [6, 0] => Encoding::ALAW,
-            [7, 0] => Encoding::MULAW,
-            _ => Encoding::default(),
+            [1, 0] => Ok(Encoding::LPCM),
+            [3, 0] => Ok(Encoding::IEEE),
+            [6, 0] => Ok(Encoding::ALAW),
+            [7, 0] => Ok(Encoding::MULAW),
+            _ => Err(Error::UnsupportedFormat(u16::from_le_bytes(bytes))),
         }
     }
 }
 
-#[derive(Default, Debug)]
+// Fields WAVE_FORMAT_EXTENSIBLE adds on top of the basic fmt chunk.
+// The sub format GUID is derived from the encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FormatExtension {
+    valid_bits_per_sample: u16,
+    channel_mask: ChannelMask,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
 pub struct HeaderFormat {
+    // Encoding of the samples, the sub format when extensible
     tag: Encoding,
     channels: u16,
     sample_rate: u32,
 
-    sample_size: u16,
+    // Bytes per frame, all channels included
+    block_align: u16,
+    bits_per_sample: u16,
+
+    extension: Option<FormatExtension>,
 }
 
 impl HeaderFormat {
@@ -57,99 +79,337 @@ impl HeaderFormat {
             tag: encoding,
             channels: channels as u16,
             sample_rate: sample_rate as u32,
-            sample_size: sample_size as u16,
+            block_align: (channels * sample_size) as u16,
+            bits_per_sample: (sample_size * 8) as u16,
+            extension: None,
         }
     }
     pub fn encoding(&mut self, encoding: Encoding) -> &Self {
         self.tag = encoding;
         self
     }
-    pub fn channels(&mut self, channels: u16) -> &Self {
+    pub fn channels(&mut self, channels: u16) -> Result<&Self, Error> {
+        self.block_align = block_align(channels, self.get_sample_size())?;
         self.channels = channels;
-        self
+        Ok(self)
     }
     pub fn sample_rate(&mut self, sample_rate: u32) -> &Self {
         self.sample_rate = sample_rate;
         self
     }
-    pub fn sample_size(&mut self, sample_size: usize) -> &Self {
-        self.sample_size = sample_size as u16;
+    pub fn sample_size(&mut self, sample_size: usize) -> Result<&Self, Error> {
+        let bits_per_sample = u16::try_from(sample_size)
+            .ok()
+            .and_then(|size| size.checked_mul(8))
+            .ok_or(Error::InvalidFormat {
+                field: "bits_per_sample",
+                expected: u16::MAX as u64,
+                found: sample_size as u64 * 8,
+            })?;
+        self.bits_per_sample(bits_per_sample)
+    }
+    pub fn bits_per_sample(&mut self, bits_per_sample: u16) -> Result<&Self, Error> {
+        self.block_align = block_align(self.channels, (bits_per_sample as usize).div_ceil(8))?;
+        self.bits_per_sample = bits_per_sample;
+        if let Some(extension) = &mut self.extension {
+            extension.valid_bits_per_sample = bits_per_sample;
+        }
+        Ok(self)
+    }
+    // Switches to WAVE_FORMAT_EXTENSIBLE with the given speaker layout
+    pub fn extensible(&mut self, channel_mask: ChannelMask) -> &Self {
+        let valid_bits_per_sample = self.get_valid_bits_per_sample();
+        self.extension = Some(FormatExtension {
+            valid_bits_per_sample,
+            channel_mask,
+        });
+        self
+    }
+    // Bits actually used out of bits_per_sample, e.g. 20 bits in a 24 bits container
+    pub fn valid_bits_per_sample(&mut self, valid_bits_per_sample: u16) -> &Self {
+        let channel_mask = self.get_channel_mask().unwrap_or_default();
+        self.extension = Some(FormatExtension {
+            valid_bits_per_sample,
+            channel_mask,
+        });
         self
     }
 
+    pub fn get_encoding(&self) -> Encoding {
+        self.tag
+    }
+    pub fn get_channels(&self) -> u16 {
+        self.channels
+    }
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    // Bytes taken by one sample of one channel, bit depth rounded up to whole bytes
+    pub fn get_sample_size(&self) -> usize {
+        (self.bits_per_sample as usize).div_ceil(8)
+    }
+    // Saturates past u32, which check rejects
     pub fn get_byte_rate(&self) -> u32 {
-        (self.channels as u32) * (self.sample_rate as u32) * (self.sample_size as u32)
+        self.sample_rate.saturating_mul(self.block_align as u32)
     }
     pub fn get_block_align(&self) -> u16 {
-        (self.channels as u16) * (self.sample_size as u16)
+        self.block_align
     }
     pub fn get_bits_per_sample(&self) -> u16 {
-        (self.sample_size as u16) * 8
+        self.bits_per_sample
+    }
+
+    pub fn is_extensible(&self) -> bool {
+        self.extension.is_some()
+    }
+    pub fn get_valid_bits_per_sample(&self) -> u16 {
+        match self.extension {
+            Some(extension) => extension.valid_bits_per_sample,
+            None => self.bits_per_sample,
+        }
+    }
+    pub fn get_channel_mask(&self) -> Option<ChannelMask> {
+        self.extension.map(|extension| extension.channel_mask)
+    }
+    pub fn get_sub_format(&self) -> Option<Guid> {
+        self.extension
+            .map(|_| Guid::from_format_tag(u16::from_le_bytes(self.tag.as_le_bytes())))
+    }
+
+    pub fn get_sample_format(&self) -> Result<SampleFormat, Error> {
+        SampleFormat::from_encoding(self.tag, self.get_sample_size())
+    }
+
+    // Checks samples of the given format can hold what the file stores
+    pub(crate) fn check_sample_format(&self, sample: SampleFormat) -> Result<(), Error> {
+        let file = self.get_sample_format()?;
+        if file != sample {
+            return Err(Error::IncompatibleSample { file, sample });
+        }
+
+        Ok(())
+    }
+
+    // Checks the fields read from disk agree with each other
+    fn validate(&self, byte_rate: u32) -> Result<(), Error> {
+        self.check()?;
+
+        if byte_rate != self.get_byte_rate() {
+            return Err(Error::InvalidFormat {
+                field: "byte_rate",
+                expected: self.get_byte_rate() as u64,
+                found: byte_rate as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Checks the layout can be used to read and write samples, whoever built it
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if self.channels == 0 {
+            return Err(Error::InvalidFormat {
+                field: "channels",
+                expected: 1,
+                found: 0,
+            });
+        }
+        if self.bits_per_sample == 0 {
+            return Err(Error::InvalidFormat {
+                field: "bits_per_sample",
+                expected: 8,
+                found: 0,
+            });
+        }
+        if self.sample_rate == 0 {
+            return Err(Error::InvalidFormat {
+                field: "sample_rate",
+                expected: 1,
+                found: 0,
+            });
+        }
+
+        let block_align = self.channels as u64 * self.get_sample_size() as u64;
+        if self.block_align as u64 != block_align {
+            return Err(Error::InvalidFormat {
+                field: "block_align",
+                expected: block_align,
+                found: self.block_align as u64,
+            });
+        }
+
+        let byte_rate = self.sample_rate as u64 * self.block_align as u64;
+        if byte_rate > u32::MAX as u64 {
+            return Err(Error::InvalidFormat {
+                field: "byte_rate",
+                expected: u32::MAX as u64,
+                found: byte_rate,
+            });
+        }
+
+        let valid_bits_per_sample = self.get_valid_bits_per_sample();
+        if valid_bits_per_sample == 0 || valid_bits_per_sample > self.bits_per_sample {
+            return Err(Error::InvalidFormat {
+                field: "valid_bits_per_sample",
+                expected: self.bits_per_sample as u64,
+                found: valid_bits_per_sample as u64,
+            });
+        }
+
+        Ok(())
     }
 }
 
+// Bytes per frame, an error rather than a wrapped value when it outgrows the u16 field
+fn block_align(channels: u16, sample_size: usize) -> Result<u16, Error> {
+    let block_align = channels as u64 * sample_size as u64;
+    u16::try_from(block_align).map_err(|_| Error::InvalidFormat {
+        field: "block_align",
+        expected: u16::MAX as u64,
+        found: block_align,
+    })
+}
+
 impl Chunk for HeaderFormat {
     fn get_be_id(&self) -> &[u8; 4] {
         FMT_TYPE
     }
 
     fn get_le_size(&self) -> u32 {
-        16
+        // Formats other than PCM always carry cbSize, even if it is zero
+        match (self.extension, self.tag) {
+            (Some(_), _) => 18 + EXTENSION_SIZE as u32,
+            (None, Encoding::LPCM) => 16,
+            (None, _) => 18,
+        }
     }
 }
 
 impl Encodable for HeaderFormat {
-    fn encode(&self) -> Vec<u8> {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
         let mut vec = Vec::new();
 
         vec.extend_from_slice(self.get_be_id());
         vec.extend_from_slice(&self.get_le_size().to_le_bytes());
-        vec.extend_from_slice(&self.tag.as_le_bytes());
+        match self.extension {
+            Some(_) => vec.extend_from_slice(&EXTENSIBLE_TAG),
+            None => vec.extend_from_slice(&self.tag.as_le_bytes()),
+        }
         vec.extend_from_slice(&self.channels.to_le_bytes());
         vec.extend_from_slice(&self.sample_rate.to_le_bytes());
         vec.extend_from_slice(&self.get_byte_rate().to_le_bytes());
         vec.extend_from_slice(&self.get_block_align().to_le_bytes());
         vec.extend_from_slice(&self.get_bits_per_sample().to_le_bytes());
 
-        vec
+        if let (Some(extension), Some(sub_format)) = (self.extension, self.get_sub_format()) {
+            vec.extend_from_slice(&EXTENSION_SIZE.to_le_bytes());
+            vec.extend_from_slice(&extension.valid_bits_per_sample.to_le_bytes());
+            vec.extend_from_slice(&extension.channel_mask.bits().to_le_bytes());
+            vec.extend_from_slice(&sub_format.as_bytes());
+        } else if self.get_le_size() == 18 {
+            vec.extend_from_slice(&0u16.to_le_bytes());
+        }
+
+        Ok(vec)
     }
 }
 
 impl Decodable for HeaderFormat {
-    fn decode_new<R: Read>(mut reader: R) -> Self {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
         let mut buffer_16 = [0; 4];
         let mut buffer_8 = [0; 2];
 
-        reader.read_exact(&mut buffer_16).unwrap();
-        let _id = FourCC::new(buffer_16).unwrap();
+        read_exact(&mut reader, &mut buffer_16, FMT_TYPE)?;
+        if buffer_16 != *FMT_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*FMT_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
 
-        reader.read_exact(&mut buffer_16).unwrap();
-        let _size = u32::from_le_bytes(buffer_16);
+        read_exact(&mut reader, &mut buffer_16, FMT_TYPE)?;
+        let size = u32::from_le_bytes(buffer_16);
+        if size < 16 {
+            return Err(Error::SizeMismatch {
+                id: FourCC::new(*FMT_TYPE),
+                expected: 16,
+                found: size as u64,
+            });
+        }
 
-        reader.read_exact(&mut buffer_8).unwrap();
-        let tag = Encoding::from_le_bytes(buffer_8);
+        read_exact(&mut reader, &mut buffer_8, FMT_TYPE)?;
+        let raw_tag = buffer_8;
 
-        reader.read_exact(&mut buffer_8).unwrap();
+        read_exact(&mut reader, &mut buffer_8, FMT_TYPE)?;
         let channels = u16::from_le_bytes(buffer_8);
 
-        reader.read_exact(&mut buffer_16).unwrap();
+        read_exact(&mut reader, &mut buffer_16, FMT_TYPE)?;
         let sample_rate = u32::from_le_bytes(buffer_16);
 
-        reader.read_exact(&mut buffer_16).unwrap();
-        let _byte_rate = u32::from_le_bytes(buffer_16);
+        read_exact(&mut reader, &mut buffer_16, FMT_TYPE)?;
+        let byte_rate = u32::from_le_bytes(buffer_16);
 
-        reader.read_exact(&mut buffer_8).unwrap();
-        let _block_align = u16::from_le_bytes(buffer_8);
+        read_exact(&mut reader, &mut buffer_8, FMT_TYPE)?;
+        let block_align = u16::from_le_bytes(buffer_8);
 
-        reader.read_exact(&mut buffer_8).unwrap();
-        let _bits_per_sample = u16::from_le_bytes(buffer_8);
+        read_exact(&mut reader, &mut buffer_8, FMT_TYPE)?;
+        let bits_per_sample = u16::from_le_bytes(buffer_8);
 
-        Self {
+        let mut extra = (size - 16) as u64;
+
+        let mut extension = None;
+        let tag = if raw_tag == EXTENSIBLE_TAG {
+            if extra < 2 + EXTENSION_SIZE as u64 {
+                return Err(Error::SizeMismatch {
+                    id: FourCC::new(*FMT_TYPE),
+                    expected: 18 + EXTENSION_SIZE as u64,
+                    found: size as u64,
+                });
+            }
+            extra -= 2 + EXTENSION_SIZE as u64;
+
+            // cbSize, already checked against the chunk size
+            read_exact(&mut reader, &mut buffer_8, FMT_TYPE)?;
+
+            read_exact(&mut reader, &mut buffer_8, FMT_TYPE)?;
+            let valid_bits_per_sample = u16::from_le_bytes(buffer_8);
+
+            read_exact(&mut reader, &mut buffer_16, FMT_TYPE)?;
+            let channel_mask = ChannelMask::from_bits(u32::from_le_bytes(buffer_16));
+
+            let mut buffer_guid = [0; 16];
+            read_exact(&mut reader, &mut buffer_guid, FMT_TYPE)?;
+            let sub_format = Guid::new(buffer_guid).get_format_tag();
+
+            extension = Some(FormatExtension {
+                valid_bits_per_sample,
+                channel_mask,
+            });
+            match sub_format {
+                Some(sub_format) => Encoding::from_le_bytes(sub_format.to_le_bytes())?,
+                None => return Err(Error::UnsupportedFormat(u16::from_le_bytes(raw_tag))),
+            }
+        } else {
+            Encoding::from_le_bytes(raw_tag)?
+        };
+
+        // Skip the extension we don't understand so the next chunk lines up
+        let skipped = io::copy(&mut reader.by_ref().take(extra), &mut io::sink())?;
+        if skipped != extra {
+            return Err(Error::TruncatedChunk(FourCC::new(*FMT_TYPE)));
+        }
+
+        let format = Self {
             tag,
             channels,
             sample_rate,
-            sample_size: 0,
-        }
+            block_align,
+            bits_per_sample,
+            extension,
+        };
+        format.validate(byte_rate)?;
+
+        Ok(format)
     }
 }
 
@@ -160,14 +420,86 @@ mod test {
     #[test]
     fn test_header_format() {
         let header = HeaderFormat::new(super::Encoding::LPCM, 2, 44100, 2);
-        let encoded = header.encode();
-        let mut decoded = HeaderFormat::decode_new(&encoded[..]);
-        decoded.sample_size(header.sample_size as usize);
+        let encoded = header.encode().unwrap();
+        let decoded = HeaderFormat::decode_new(&encoded[..]).unwrap();
 
-        let reincoded = decoded.encode();
+        let reincoded = decoded.encode().unwrap();
 
         println!("{:?}", header);
         println!("{:?}", decoded);
         assert_eq!(encoded, reincoded);
+        assert_eq!(decoded.get_bits_per_sample(), 16);
+        assert_eq!(decoded.get_block_align(), 4);
+    }
+
+    #[test]
+    fn test_extensible() {
+        let mut header = HeaderFormat::new(super::Encoding::LPCM, 6, 48000, 4);
+        header.extensible(ChannelMask::SURROUND_5_1);
+        header.valid_bits_per_sample(24);
+
+        let encoded = header.encode().unwrap();
+        assert_eq!(encoded.len(), 8 + 40);
+        assert_eq!(encoded[8..10], [0xfe, 0xff]);
+        assert_eq!(encoded[24..26], [22, 0]);
+
+        let decoded = HeaderFormat::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(decoded.get_encoding(), super::Encoding::LPCM);
+        assert_eq!(decoded.get_valid_bits_per_sample(), 24);
+        assert_eq!(decoded.get_channel_mask(), Some(ChannelMask::SURROUND_5_1));
+        assert_eq!(decoded.get_sub_format(), Some(Guid::from_format_tag(1)));
+    }
+
+    #[test]
+    fn test_inconsistent_block_align() {
+        let mut encoded = HeaderFormat::new(super::Encoding::LPCM, 2, 44100, 2)
+            .encode()
+            .unwrap();
+        // 24 bits per sample over 2 channels can't fit in 4 bytes per frame
+        encoded[22] = 24;
+
+        let decoded = HeaderFormat::decode_new(&encoded[..]);
+        assert!(matches!(
+            decoded,
+            Err(Error::InvalidFormat {
+                field: "block_align",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_overflowing_fields() {
+        let mut encoded = HeaderFormat::new(super::Encoding::LPCM, 2, 44100, 2)
+            .encode()
+            .unwrap();
+        encoded[12..16].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+
+        let decoded = HeaderFormat::decode_new(&encoded[..]);
+        assert!(matches!(
+            decoded,
+            Err(Error::InvalidFormat {
+                field: "byte_rate",
+                ..
+            })
+        ));
+
+        let mut header = HeaderFormat::new(super::Encoding::IEEE, 2, 44100, 8);
+        assert!(header.channels(u16::MAX).is_err());
+        assert!(header.sample_size(40_000).is_err());
+        // Left as it was
+        assert_eq!(header.get_block_align(), 16);
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        let mut encoded = HeaderFormat::new(super::Encoding::LPCM, 2, 44100, 2)
+            .encode()
+            .unwrap();
+        encoded[8] = 0x55;
+
+        let decoded = HeaderFormat::decode_new(&encoded[..]);
+        assert!(matches!(decoded, Err(Error::UnsupportedFormat(0x55))));
     }
 }
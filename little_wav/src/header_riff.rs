@@ -1,34 +1,86 @@
 use crate::{
     chunk::Chunk,
-    codable::{Decodable, Encodable},
+    codable::{read_exact, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
 };
 use std::io::Read;
 
-const RIFF_ID: &[u8; 4] = b"RIFF";
+pub(crate) const RIFF_ID: &[u8; 4] = b"RIFF";
+const RF64_ID: &[u8; 4] = b"RF64";
+const BW64_ID: &[u8; 4] = b"BW64";
 const RIFF_TYPE: &[u8; 4] = b"WAVE";
 
+// Outer chunk of the file. RF64 and BW64 leave their 32-bit sizes at 0xFFFFFFFF
+// and keep the real ones in a ds64 chunk right after the form type.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum RiffForm {
+    #[default]
+    Riff,
+    Rf64,
+    Bw64,
+}
+
+impl RiffForm {
+    pub fn is_64_bit(self) -> bool {
+        self != RiffForm::Riff
+    }
+
+    pub(crate) fn get_be_id(self) -> &'static [u8; 4] {
+        match self {
+            RiffForm::Riff => RIFF_ID,
+            RiffForm::Rf64 => RF64_ID,
+            RiffForm::Bw64 => BW64_ID,
+        }
+    }
+}
+
 // RIFF Header struct is
-#[derive(Default, Debug)]
+#[derive(Clone, Copy, Default, Debug)]
 pub struct HeaderRiff {
-    size: u32,
+    form: RiffForm,
+    size: u64,
 }
 
 impl Chunk for HeaderRiff {
     fn get_be_id(&self) -> &[u8; 4] {
-        RIFF_ID
+        self.form.get_be_id()
     }
 
     fn get_le_size(&self) -> u32 {
-        self.size
+        if self.form.is_64_bit() {
+            u32::MAX
+        } else {
+            self.size as u32
+        }
     }
 }
 
 impl HeaderRiff {
     pub fn new() -> Self {
-        Self { size: 0 }
+        Self {
+            form: RiffForm::Riff,
+            size: 0,
+        }
     }
     pub fn set_size(&mut self, header_size: usize, samples_len: usize, sample_size: usize) {
-        self.size = (header_size + sample_size * samples_len) as u32;
+        let body_size = sample_size as u64 * samples_len as u64;
+        self.size = header_size as u64 + body_size + body_size % 2;
+    }
+    pub fn set_form(&mut self, form: RiffForm) {
+        self.form = form;
+    }
+
+    pub fn get_form(&self) -> RiffForm {
+        self.form
+    }
+    // Real size, wherever it is stored
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+    // Sizes of RF64 and BW64 files come from ds64
+    pub(crate) fn resize(&mut self, size: u64) {
+        self.size = size;
     }
 
     // returns a static type for RIFF chunk
@@ -38,35 +90,46 @@ impl HeaderRiff {
 }
 
 impl Encodable for HeaderRiff {
-    fn encode(&self) -> Vec<u8> {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
         let mut vec = Vec::new();
 
         vec.extend_from_slice(self.get_be_id());
-        vec.extend_from_slice(&self.size.to_le_bytes());
+        vec.extend_from_slice(&self.get_le_size().to_le_bytes());
         vec.extend_from_slice(self.get_be_type());
 
-        vec
+        Ok(vec)
     }
 }
 
 impl Decodable for HeaderRiff {
-    fn decode_new<R: Read>(mut reader: R) -> Self {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
         let mut buffer_16 = [0; 4];
 
-        reader.read_exact(&mut buffer_16).unwrap();
-        if buffer_16 != *RIFF_ID {
-            panic!("RIFF ID not found");
-        }
-
-        reader.read_exact(&mut buffer_16).unwrap();
-        let size = u32::from_le_bytes(buffer_16);
-
-        reader.read_exact(&mut buffer_16).unwrap();
+        read_exact(&mut reader, &mut buffer_16, RIFF_ID)?;
+        let form = match &buffer_16 {
+            RIFF_ID => RiffForm::Riff,
+            RF64_ID => RiffForm::Rf64,
+            BW64_ID => RiffForm::Bw64,
+            _ => {
+                return Err(Error::BadMagic {
+                    expected: FourCC::new(*RIFF_ID),
+                    found: FourCC::new(buffer_16),
+                })
+            }
+        };
+
+        read_exact(&mut reader, &mut buffer_16, RIFF_ID)?;
+        let size = u32::from_le_bytes(buffer_16) as u64;
+
+        read_exact(&mut reader, &mut buffer_16, RIFF_ID)?;
         if buffer_16 != *RIFF_TYPE {
-            panic!("RIFF Type not found");
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*RIFF_TYPE),
+                found: FourCC::new(buffer_16),
+            });
         }
 
-        Self { size }
+        Ok(Self { form, size })
     }
 }
 
@@ -77,8 +140,33 @@ mod test {
     #[test]
     fn test_decode() {
         let encoded = [82, 73, 70, 70, 2, 0, 0, 0, 87, 65, 86, 69];
-        let header = HeaderRiff::decode_new(&encoded[..]);
+        let header = HeaderRiff::decode_new(&encoded[..]).unwrap();
 
         assert_eq!(header.size, 2);
     }
+
+    #[test]
+    fn test_decode_rf64() {
+        let encoded = [82, 70, 54, 52, 255, 255, 255, 255, 87, 65, 86, 69];
+        let header = HeaderRiff::decode_new(&encoded[..]).unwrap();
+
+        assert_eq!(header.get_form(), RiffForm::Rf64);
+        assert_eq!(header.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn test_decode_bad_magic() {
+        let encoded = [82, 73, 70, 70, 2, 0, 0, 0, 65, 86, 73, 32];
+        let header = HeaderRiff::decode_new(&encoded[..]);
+
+        assert!(matches!(header, Err(Error::BadMagic { .. })));
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let encoded = [82, 73, 70, 70, 2, 0];
+        let header = HeaderRiff::decode_new(&encoded[..]);
+
+        assert!(matches!(header, Err(Error::TruncatedChunk(_))));
+    }
 }
@@ -0,0 +1,128 @@
+use crate::{chunk::RawChunk, four_cc::FourCC};
+
+pub(crate) const IXML_TYPE: &[u8; 4] = b"iXML";
+// Adobe XMP packet
+pub(crate) const XMP_TYPE: &[u8; 4] = b"_PMX";
+
+// iXML production metadata as written by field recorders. The XML is kept as is,
+// the few fields read out of it are looked up on demand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ixml {
+    xml: String,
+}
+
+impl Ixml {
+    pub fn new(xml: &str) -> Self {
+        Self {
+            xml: xml.to_string(),
+        }
+    }
+    pub fn as_str(&self) -> &str {
+        &self.xml
+    }
+
+    pub fn project(&self) -> Option<String> {
+        first_element(&self.xml, "PROJECT")
+    }
+    pub fn scene(&self) -> Option<String> {
+        first_element(&self.xml, "SCENE")
+    }
+    pub fn take(&self) -> Option<String> {
+        first_element(&self.xml, "TAKE")
+    }
+    pub fn tape(&self) -> Option<String> {
+        first_element(&self.xml, "TAPE")
+    }
+    // Name of each track of TRACK_LIST with its 1-based CHANNEL_INDEX
+    pub fn track_names(&self) -> Vec<(u16, String)> {
+        let Some(list) = elements(&self.xml, "TRACK_LIST").next() else {
+            return Vec::new();
+        };
+
+        elements(list, "TRACK")
+            .filter_map(|track| {
+                let index = first_element(track, "CHANNEL_INDEX")?.trim().parse().ok()?;
+                Some((index, first_element(track, "NAME").unwrap_or_default()))
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn is_ixml(chunk: &RawChunk) -> bool {
+    chunk.id() == FourCC::new(*IXML_TYPE)
+}
+pub(crate) fn is_xmp(chunk: &RawChunk) -> bool {
+    chunk.id() == FourCC::new(*XMP_TYPE)
+}
+
+// XML text of a chunk, writers often pad it with nulls
+pub(crate) fn chunk_text(chunk: &RawChunk) -> String {
+    let text = String::from_utf8_lossy(chunk.data());
+    text.trim_end_matches('\0').to_string()
+}
+
+fn first_element(xml: &str, name: &str) -> Option<String> {
+    elements(xml, name).next().map(unescape)
+}
+
+// Contents of each `<name>...</name>` in turn, nesting of the same name isn't supported
+fn elements<'a>(xml: &'a str, name: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let mut rest = xml;
+
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let content = &rest[start..end];
+        rest = &rest[end + close.len()..];
+        Some(content)
+    })
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<BWFXML>
+  <PROJECT>Night &amp; Day</PROJECT>
+  <SCENE>12A</SCENE>
+  <TAKE>3</TAKE>
+  <TRACK_LIST>
+    <TRACK_COUNT>2</TRACK_COUNT>
+    <TRACK><CHANNEL_INDEX>1</CHANNEL_INDEX><NAME>Boom</NAME></TRACK>
+    <TRACK><CHANNEL_INDEX>2</CHANNEL_INDEX><NAME>Lav</NAME></TRACK>
+  </TRACK_LIST>
+</BWFXML>";
+
+    #[test]
+    fn reads_fields() {
+        let ixml = Ixml::new(XML);
+
+        assert_eq!(ixml.project().as_deref(), Some("Night & Day"));
+        assert_eq!(ixml.scene().as_deref(), Some("12A"));
+        assert_eq!(ixml.take().as_deref(), Some("3"));
+        assert_eq!(ixml.tape(), None);
+        assert_eq!(
+            ixml.track_names(),
+            [(1, "Boom".to_string()), (2, "Lav".to_string())]
+        );
+    }
+
+    #[test]
+    fn trims_null_padding() {
+        let chunk = RawChunk::new(FourCC::new(*IXML_TYPE), b"<BWFXML/>\0\0\0".to_vec());
+
+        assert_eq!(chunk_text(&chunk), "<BWFXML/>");
+        assert!(Ixml::new(&chunk_text(&chunk)).track_names().is_empty());
+    }
+}
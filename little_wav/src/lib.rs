@@ -1,10 +1,58 @@
+mod aiff;
+mod au;
+mod bext;
+mod channel_mask;
 mod chunk;
+mod chunk_reader;
 mod codable;
+mod codec;
+mod container;
+mod convert;
+mod cue;
+mod error;
 mod four_cc;
+mod g711;
+mod guid;
 mod header_data;
+mod header_ds64;
+mod header_fact;
 mod header_format;
 mod header_riff;
+mod ixml;
+mod lint;
+mod list_info;
+mod raw;
+mod repair;
+mod rifx;
+mod sample;
+mod sampler;
 mod wav;
+mod wav_reader;
+mod wav_ref;
+mod wav_writer;
+mod wave64;
 
+pub use bext::{Bext, Loudness};
+pub use channel_mask::ChannelMask;
+pub use chunk::{Chunk, ChunkHeader, ChunkId, RawChunk};
+pub use chunk_reader::ChunkReader;
+pub use codable::{Codable, Decodable, Encodable};
+pub use container::Container;
+pub use convert::{convert_samples, Dither};
+pub use cue::Marker;
+pub use error::Error;
 pub use four_cc::FourCC;
+pub use g711::{alaw_decode, alaw_encode, mulaw_decode, mulaw_encode};
+pub use guid::Guid;
+pub use header_format::{Encoding, HeaderFormat};
+pub use header_riff::RiffForm;
+pub use ixml::Ixml;
+pub use lint::{lint, Diagnostic, Severity};
+pub use list_info::ListInfo;
+pub use repair::{repair_file, Fix};
+pub use sample::{ByteOrder, Sample, SampleFormat, I24};
+pub use sampler::{Loop, LoopKind, Sampler};
 pub use wav::Wav;
+pub use wav_reader::{Blocks, WavReader};
+pub use wav_ref::{Samples, WavRef};
+pub use wav_writer::WavWriter;
@@ -0,0 +1,550 @@
+use crate::{
+    bext::{Bext, BEXT_TYPE},
+    chunk::{decode_chunk, RawChunk},
+    codable::Decodable,
+    cue::{self, u32_at, CUE_TYPE},
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+    header_ds64::{HeaderDs64, DS64_TYPE},
+    header_fact::FACT_TYPE,
+    header_format::{Encoding, HeaderFormat, FMT_TYPE},
+    header_riff::RiffForm,
+    list_info::{ListInfo, LIST_TYPE},
+    sampler::{Sampler, SMPL_TYPE},
+};
+use std::{fmt, io::Read};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    // Readers cope, but the file isn't what the spec asks for
+    Warning,
+    // Readers, this one included, may reject the file or misread it
+    Error,
+}
+
+// One problem found in a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // Where the problem is, from the start of the file
+    pub offset: u64,
+    // Chunk it's in, None for the file as a whole
+    pub chunk: Option<FourCC>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.chunk {
+            Some(id) => write!(
+                f,
+                "{} at {:#x} in '{}': {}",
+                severity, self.offset, id, self.message
+            ),
+            None => write!(f, "{} at {:#x}: {}", severity, self.offset, self.message),
+        }
+    }
+}
+
+// Checks a RIFF/WAVE file from top to bottom, reporting every problem instead of stopping
+// at the first one. Only I/O errors fail.
+pub fn lint<R: Read>(mut reader: R) -> Result<Vec<Diagnostic>, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    Ok(lint_bytes(&bytes))
+}
+
+// A chunk as found on disk, body cut short if the file is
+struct Found<'a> {
+    id: FourCC,
+    offset: u64,
+    body: &'a [u8],
+}
+
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn push(&mut self, severity: Severity, offset: u64, chunk: Option<FourCC>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            offset,
+            chunk,
+            message,
+        });
+    }
+    fn error(&mut self, offset: u64, chunk: Option<FourCC>, message: String) {
+        self.push(Severity::Error, offset, chunk, message);
+    }
+    fn warn(&mut self, offset: u64, chunk: Option<FourCC>, message: String) {
+        self.push(Severity::Warning, offset, chunk, message);
+    }
+}
+
+pub(crate) fn lint_bytes(bytes: &[u8]) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        diagnostics: Vec::new(),
+    };
+    let len = bytes.len() as u64;
+
+    if bytes.len() < 12 {
+        linter.error(
+            0,
+            None,
+            format!("{} bytes is too short for a RIFF header", len),
+        );
+        return linter.diagnostics;
+    }
+    let form = match &bytes[..4] {
+        b"RIFF" => RiffForm::Riff,
+        b"RF64" => RiffForm::Rf64,
+        b"BW64" => RiffForm::Bw64,
+        magic => {
+            let found = FourCC::new(magic.try_into().expect("4 bytes"));
+            linter.error(0, None, format!("'{}' is not a RIFF/WAVE file", found));
+            return linter.diagnostics;
+        }
+    };
+    let riff_id = Some(FourCC::new(*form.get_be_id()));
+    if &bytes[8..12] != b"WAVE" {
+        let found = FourCC::new(bytes[8..12].try_into().expect("4 bytes"));
+        linter.error(8, riff_id, format!("form type is '{}', not 'WAVE'", found));
+    }
+
+    // RF64 and BW64 keep their sizes in ds64, which has to come first
+    let mut ds64 = None;
+    if form.is_64_bit() {
+        if bytes.get(12..16) == Some(DS64_TYPE) {
+            match HeaderDs64::decode_new(&bytes[12..]) {
+                Ok(parsed) => ds64 = Some(parsed),
+                Err(err) => linter.error(12, Some(FourCC::new(*DS64_TYPE)), err.to_string()),
+            }
+        } else {
+            linter.error(12, riff_id, "ds64 isn't the first chunk".to_string());
+        }
+    }
+    let riff_size = match (&ds64, form.is_64_bit()) {
+        (Some(ds64), _) => Some(ds64.get_riff_size()),
+        (None, true) => None,
+        (None, false) => Some(u32::from_le_bytes(bytes[4..8].try_into().expect("4 bytes")) as u64),
+    };
+    if let Some(riff_size) = riff_size {
+        let declared = riff_size.saturating_add(8);
+        if declared > len {
+            linter.error(
+                4,
+                riff_id,
+                format!(
+                    "RIFF size says {} bytes but the file holds {}",
+                    declared, len
+                ),
+            );
+        } else if declared < len {
+            linter.warn(
+                4,
+                riff_id,
+                format!("{} bytes past the end of RIFF", len - declared),
+            );
+        }
+    }
+
+    let chunks = walk(&mut linter, bytes, ds64.as_ref());
+    check_chunks(&mut linter, &chunks);
+
+    linter.diagnostics
+}
+
+// Lists the chunks of the file, the whole of it whatever the RIFF size says
+fn walk<'a>(linter: &mut Linter, bytes: &'a [u8], ds64: Option<&HeaderDs64>) -> Vec<Found<'a>> {
+    let len = bytes.len() as u64;
+    let mut chunks = Vec::new();
+    let mut position = 12u64;
+
+    while position + 8 <= len {
+        let at = position as usize;
+        let raw_id: [u8; 4] = bytes[at..at + 4].try_into().expect("4 bytes");
+        let id = FourCC::new(raw_id);
+        if !is_plausible_id(&raw_id) {
+            linter.error(
+                position,
+                None,
+                "no chunk id where one should be, stopped here".to_string(),
+            );
+            return chunks;
+        }
+
+        let size = match (ds64, u32_at(bytes, at + 4).expect("8 bytes left")) {
+            (Some(ds64), u32::MAX) => ds64.get_chunk_size(id).unwrap_or(u32::MAX as u64),
+            (_, size) => size as u64,
+        };
+        let body_start = position + 8;
+        let body_end = body_start.saturating_add(size);
+
+        if body_end > len {
+            linter.error(
+                position + 4,
+                Some(id),
+                format!(
+                    "size of {} bytes runs {} bytes past the end of the file",
+                    size,
+                    body_end - len
+                ),
+            );
+            chunks.push(Found {
+                id,
+                offset: position,
+                body: &bytes[body_start as usize..],
+            });
+            return chunks;
+        }
+        chunks.push(Found {
+            id,
+            offset: position,
+            body: &bytes[body_start as usize..body_end as usize],
+        });
+
+        position = body_end;
+        if size % 2 == 1 {
+            let padded_next = bytes.get(body_end as usize + 1..body_end as usize + 5);
+            let unpadded_next = bytes.get(body_end as usize..body_end as usize + 4);
+            let padded_ok = padded_next.is_some_and(is_plausible_id);
+            let unpadded_ok = unpadded_next.is_some_and(is_plausible_id);
+
+            if body_end == len {
+                linter.warn(
+                    body_end,
+                    Some(id),
+                    "odd-sized chunk ends the file without its pad byte".to_string(),
+                );
+            } else if unpadded_ok && !padded_ok {
+                linter.warn(
+                    body_end,
+                    Some(id),
+                    "odd-sized chunk isn't followed by a pad byte".to_string(),
+                );
+            } else {
+                position += 1;
+            }
+        }
+    }
+
+    if position < len {
+        linter.warn(
+            position,
+            None,
+            format!("{} stray bytes at the end of the file", len - position),
+        );
+    }
+
+    chunks
+}
+
+// Chunk ids are printable ASCII
+fn is_plausible_id(id: &[u8]) -> bool {
+    id.iter().all(|byte| (0x20..0x7f).contains(byte))
+}
+
+fn check_chunks(linter: &mut Linter, chunks: &[Found]) {
+    let first = |id: &[u8; 4]| chunks.iter().position(|found| found.id.as_array() == id);
+
+    for id in [FMT_TYPE, DATA_TYPE, FACT_TYPE, DS64_TYPE] {
+        for extra in chunks
+            .iter()
+            .filter(|found| found.id.as_array() == id)
+            .skip(1)
+        {
+            linter.warn(
+                extra.offset,
+                Some(extra.id),
+                "more than one chunk of this kind, only the first one counts".to_string(),
+            );
+        }
+    }
+
+    let format = match first(FMT_TYPE) {
+        Some(index) => check_format(linter, &chunks[index]),
+        None => {
+            linter.error(12, None, "no fmt chunk".to_string());
+            None
+        }
+    };
+    let frames = match first(DATA_TYPE) {
+        Some(index) => {
+            let data = &chunks[index];
+            if first(FMT_TYPE).is_some_and(|fmt| fmt > index) {
+                linter.warn(
+                    data.offset,
+                    Some(data.id),
+                    "data comes before fmt, streaming readers can't play it".to_string(),
+                );
+            }
+            format.as_ref().map(|format| {
+                let block_align = (format.get_block_align() as u64).max(1);
+                let size = data.body.len() as u64;
+                if !size.is_multiple_of(block_align) {
+                    linter.warn(
+                        data.offset,
+                        Some(data.id),
+                        format!(
+                            "{} bytes don't make whole frames of {} bytes",
+                            size, block_align
+                        ),
+                    );
+                }
+                size / block_align
+            })
+        }
+        None => {
+            linter.error(12, None, "no data chunk".to_string());
+            None
+        }
+    };
+
+    match (first(FACT_TYPE), &format) {
+        (None, Some(format)) if format.get_encoding() != Encoding::LPCM => {
+            linter.warn(
+                12,
+                None,
+                format!("{:?} files need a fact chunk", format.get_encoding()),
+            );
+        }
+        (Some(index), _) => {
+            let fact = &chunks[index];
+            match (u32_at(fact.body, 0), frames) {
+                (None, _) => linter.error(fact.offset, Some(fact.id), "too short".to_string()),
+                // Past u32 the count is 0xFFFFFFFF and the real one goes in ds64
+                (Some(length), Some(frames)) if length as u64 != frames && length != u32::MAX => {
+                    linter.warn(
+                        fact.offset + 8,
+                        Some(fact.id),
+                        format!("counts {} frames but data holds {}", length, frames),
+                    );
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+
+    for found in chunks {
+        let raw = RawChunk::new(found.id, found.body.to_vec());
+        match found.id.as_array() {
+            LIST_TYPE if ListInfo::is_info(&raw) => {
+                if let Err(err) = ListInfo::from_chunk(&raw) {
+                    linter.warn(found.offset, Some(found.id), err.to_string());
+                }
+            }
+            BEXT_TYPE => {
+                if let Err(err) = Bext::from_chunk(&raw) {
+                    linter.warn(found.offset, Some(found.id), err.to_string());
+                }
+            }
+            CUE_TYPE => {
+                let adtl = chunks
+                    .iter()
+                    .map(|found| RawChunk::new(found.id, found.body.to_vec()))
+                    .find(cue::is_adtl);
+                match cue::decode_markers(&raw, adtl.as_ref()) {
+                    Ok(markers) => {
+                        for marker in markers {
+                            let end = marker.position as u64 + marker.length as u64;
+                            if frames.is_some_and(|frames| end > frames) {
+                                linter.warn(
+                                    found.offset,
+                                    Some(found.id),
+                                    format!("cue point {} is past the last frame", marker.id),
+                                );
+                            }
+                        }
+                    }
+                    Err(err) => linter.warn(found.offset, Some(found.id), err.to_string()),
+                }
+            }
+            SMPL_TYPE => match Sampler::from_chunk(&raw) {
+                Ok(sampler) => {
+                    for (index, sample_loop) in sampler.loops.iter().enumerate() {
+                        if sample_loop.start > sample_loop.end {
+                            linter.warn(
+                                found.offset,
+                                Some(found.id),
+                                format!("loop {} ends before it starts", index),
+                            );
+                        } else if frames.is_some_and(|frames| sample_loop.end as u64 >= frames) {
+                            linter.warn(
+                                found.offset,
+                                Some(found.id),
+                                format!("loop {} runs past the last frame", index),
+                            );
+                        }
+                    }
+                }
+                Err(err) => linter.warn(found.offset, Some(found.id), err.to_string()),
+            },
+            _ => {}
+        }
+    }
+}
+
+// Field by field, so that every inconsistency gets reported, then through the fmt decoder
+fn check_format(linter: &mut Linter, found: &Found) -> Option<HeaderFormat> {
+    let body = found.body;
+    let offset = found.offset + 8;
+    let id = Some(found.id);
+    if body.len() < 16 {
+        linter.error(
+            found.offset,
+            id,
+            format!("{} bytes is too short", body.len()),
+        );
+        return None;
+    }
+
+    let u16_at = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+    let channels = u16_at(2);
+    let sample_rate = u32_at(body, 4).expect("16 bytes");
+    let byte_rate = u32_at(body, 8).expect("16 bytes");
+    let block_align = u16_at(12);
+    let bits_per_sample = u16_at(14);
+
+    let before = linter.diagnostics.len();
+    if channels == 0 {
+        linter.error(offset + 2, id, "no channels".to_string());
+    }
+    if sample_rate == 0 {
+        linter.error(offset + 4, id, "sample rate is 0".to_string());
+    }
+    let expected_block_align = channels as u64 * (bits_per_sample as u64).div_ceil(8);
+    if block_align as u64 != expected_block_align {
+        linter.error(
+            offset + 12,
+            id,
+            format!(
+                "block_align is {} but {} channels of {} bits take {}",
+                block_align, channels, bits_per_sample, expected_block_align
+            ),
+        );
+    }
+    if byte_rate as u64 != sample_rate as u64 * block_align as u64 {
+        linter.error(
+            offset + 8,
+            id,
+            format!(
+                "byte_rate is {} but {} Hz of {} byte frames make {}",
+                byte_rate,
+                sample_rate,
+                block_align,
+                sample_rate as u64 * block_align as u64
+            ),
+        );
+    }
+    if linter.diagnostics.len() > before {
+        return None;
+    }
+
+    match decode_chunk::<HeaderFormat>(found.id, body) {
+        Ok(format) => Some(format),
+        Err(err) => {
+            linter.error(found.offset, id, err.to_string());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{codable::Encodable, cue::Marker, wav::Wav};
+
+    fn clean() -> Vec<u8> {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"note"), vec![1, 2, 3]));
+        wav.push_body(vec![0; 8]);
+        wav.encode().unwrap()
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<(Severity, u64)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.offset))
+            .collect()
+    }
+
+    #[test]
+    fn passes_clean_files() {
+        assert!(lint(&clean()[..]).unwrap().is_empty());
+
+        let mut wav = Wav::<f32>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_form(RiffForm::Bw64);
+        wav.push_body(vec![0.5; 3]);
+        assert_eq!(lint(&wav.encode().unwrap()[..]).unwrap(), []);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mut file = clean();
+        // Wrong RIFF size, byte_rate and block_align
+        file[4..8].copy_from_slice(&1_000u32.to_le_bytes());
+        file[28..32].copy_from_slice(&1u32.to_le_bytes());
+        file[32..34].copy_from_slice(&3u16.to_le_bytes());
+
+        let diagnostics = lint(&file[..]).unwrap();
+        assert_eq!(
+            messages(&diagnostics),
+            [
+                (Severity::Error, 4),
+                (Severity::Error, 32),
+                (Severity::Error, 28),
+            ]
+        );
+        assert_eq!(diagnostics[1].chunk, Some(FourCC::new(*FMT_TYPE)));
+        assert_eq!(
+            diagnostics[1].message,
+            "block_align is 3 but 2 channels of 16 bits take 4"
+        );
+    }
+
+    #[test]
+    fn finds_missing_pad_and_short_data() {
+        let mut file = clean();
+        // Drop the pad byte after "note" and cut into data
+        file.remove(36 + 8 + 3);
+        file.truncate(file.len() - 3);
+        let riff_size = file.len() as u32 - 8;
+        file[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let diagnostics = lint(&file[..]).unwrap();
+        assert_eq!(
+            messages(&diagnostics),
+            [
+                (Severity::Warning, 47),
+                (Severity::Error, 51),
+                // What's left of data ends mid-frame
+                (Severity::Warning, 47),
+            ]
+        );
+        assert_eq!(diagnostics[1].chunk, Some(FourCC::new(*DATA_TYPE)));
+    }
+
+    #[test]
+    fn checks_markers_and_fact() {
+        let mut wav = Wav::<i16>::new(Encoding::MULAW, 1, 8_000);
+        wav.push_body(vec![0; 4]);
+        wav.set_markers(vec![Marker::new(1, 2), Marker::region(2, 2, 10)])
+            .unwrap();
+
+        let diagnostics = wav.validate().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].chunk, Some(FourCC::new(*CUE_TYPE)));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning at 0x32 in 'cue ': cue point 2 is past the last frame"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    chunk::{decode_chunk, padded, Chunk, RawChunk},
+    codable::{read_exact, read_partial, Decodable, Encodable},
+    error::Error,
+    four_cc::FourCC,
+};
+use std::io::Read;
+
+pub(crate) const LIST_TYPE: &[u8; 4] = b"LIST";
+const INFO_TYPE: &[u8; 4] = b"INFO";
+
+// LIST chunk of type INFO: text tags such as title, artist or software, in file order
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct ListInfo {
+    entries: Vec<(FourCC, String)>,
+}
+
+impl ListInfo {
+    pub const TITLE: FourCC = FourCC::new(*b"INAM");
+    pub const ARTIST: FourCC = FourCC::new(*b"IART");
+    pub const ALBUM: FourCC = FourCC::new(*b"IPRD");
+    pub const TRACK: FourCC = FourCC::new(*b"ITRK");
+    pub const GENRE: FourCC = FourCC::new(*b"IGNR");
+    pub const COMMENT: FourCC = FourCC::new(*b"ICMT");
+    pub const COPYRIGHT: FourCC = FourCC::new(*b"ICOP");
+    // Usually YYYY-MM-DD
+    pub const CREATION_DATE: FourCC = FourCC::new(*b"ICRD");
+    pub const ENGINEER: FourCC = FourCC::new(*b"IENG");
+    pub const KEYWORDS: FourCC = FourCC::new(*b"IKEY");
+    pub const SOFTWARE: FourCC = FourCC::new(*b"ISFT");
+    pub const SOURCE: FourCC = FourCC::new(*b"ISRC");
+    pub const SUBJECT: FourCC = FourCC::new(*b"ISBJ");
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: FourCC) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| *entry == id)
+            .map(|(_, text)| text.as_str())
+    }
+    // Replaces the text of an existing tag in place, or appends a new one
+    pub fn set(&mut self, id: FourCC, text: &str) -> &Self {
+        match self.entries.iter_mut().find(|(entry, _)| *entry == id) {
+            Some((_, entry)) => *entry = text.to_string(),
+            None => self.entries.push((id, text.to_string())),
+        }
+        self
+    }
+    pub fn remove(&mut self, id: FourCC) -> &Self {
+        self.entries.retain(|(entry, _)| *entry != id);
+        self
+    }
+    pub fn entries(&self) -> &[(FourCC, String)] {
+        &self.entries
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Whether a chunk kept as is holds a LIST/INFO
+    pub(crate) fn is_info(chunk: &RawChunk) -> bool {
+        chunk.id() == FourCC::new(*LIST_TYPE) && chunk.data().get(..4) == Some(INFO_TYPE)
+    }
+
+    pub(crate) fn from_chunk(chunk: &RawChunk) -> Result<Self, Error> {
+        decode_chunk(FourCC::new(*LIST_TYPE), chunk.data())
+    }
+    pub(crate) fn to_chunk(&self) -> RawChunk {
+        let mut body = INFO_TYPE.to_vec();
+        for (id, text) in &self.entries {
+            // Null terminated, the terminator counts in the size but the pad doesn't
+            let size = text.len() + 1;
+            body.extend_from_slice(id.as_array());
+            body.extend_from_slice(&(size as u32).to_le_bytes());
+            body.extend_from_slice(text.as_bytes());
+            body.resize(body.len() + padded(size as u64) as usize - text.len(), 0);
+        }
+
+        RawChunk::new(FourCC::new(*LIST_TYPE), body)
+    }
+}
+
+impl Chunk for ListInfo {
+    fn get_be_id(&self) -> &[u8; 4] {
+        LIST_TYPE
+    }
+
+    fn get_le_size(&self) -> u32 {
+        let entries: u64 = self
+            .entries
+            .iter()
+            .map(|(_, text)| 8 + padded(text.len() as u64 + 1))
+            .sum();
+        (4 + entries) as u32
+    }
+}
+
+impl Encodable for ListInfo {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        self.to_chunk().encode()
+    }
+}
+
+impl Decodable for ListInfo {
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut buffer_16 = [0; 4];
+
+        read_exact(&mut reader, &mut buffer_16, LIST_TYPE)?;
+        if buffer_16 != *LIST_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*LIST_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
+        read_exact(&mut reader, &mut buffer_16, LIST_TYPE)?;
+        let size = u32::from_le_bytes(buffer_16) as u64;
+        read_exact(&mut reader, &mut buffer_16, LIST_TYPE)?;
+        if buffer_16 != *INFO_TYPE {
+            return Err(Error::BadMagic {
+                expected: FourCC::new(*INFO_TYPE),
+                found: FourCC::new(buffer_16),
+            });
+        }
+
+        let size = size.saturating_sub(4);
+        let body = read_partial(&mut reader, size)?;
+        if (body.len() as u64) < size {
+            return Err(Error::TruncatedChunk(FourCC::new(*LIST_TYPE)));
+        }
+
+        let mut entries = Vec::new();
+        let mut at = 0;
+        while let Some(header) = body.get(at..at + 8) {
+            let id = FourCC::new(header[..4].try_into().expect("4 bytes"));
+            let size = u32::from_le_bytes(header[4..].try_into().expect("4 bytes")) as usize;
+            let mut text = body
+                .get(at + 8..at + 8 + size)
+                .ok_or(Error::TruncatedChunk(FourCC::new(*LIST_TYPE)))?;
+            // Writers disagree on terminators and padding, trailing nulls are all dropped.
+            // The pad byte after the last entry is often left out too.
+            while let [rest @ .., 0] = text {
+                text = rest;
+            }
+
+            entries.push((id, String::from_utf8_lossy(text).into_owned()));
+            at += 8 + padded(size as u64) as usize;
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut info = ListInfo::new();
+        info.set(ListInfo::TITLE, "Tone");
+        info.set(ListInfo::SOFTWARE, "little_wav");
+        info.set(ListInfo::TITLE, "Sine");
+
+        let encoded = info.encode().unwrap();
+        assert_eq!(encoded[..12], *b"LIST\x26\0\0\0INFO");
+        // "Sine" plus its terminator is odd, so a pad byte follows
+        assert_eq!(encoded[12..26], *b"INAM\x05\0\0\0Sine\0\0");
+        assert_eq!(info.get_le_size() as usize, encoded.len() - 8);
+
+        let decoded = ListInfo::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded, info);
+        assert_eq!(decoded.get(ListInfo::TITLE), Some("Sine"));
+        assert_eq!(decoded.get(ListInfo::ARTIST), None);
+    }
+
+    #[test]
+    fn reads_unpadded_last_entry() {
+        let mut info = ListInfo::new();
+        info.set(ListInfo::ARTIST, "Me");
+        info.set(ListInfo::TITLE, "Sine");
+        let mut encoded = info.encode().unwrap();
+        // Drop the pad byte after "Sine" and count it out of the LIST size
+        encoded.pop();
+        let size = encoded.len() as u32 - 8;
+        encoded[4..8].copy_from_slice(&size.to_le_bytes());
+
+        assert_eq!(ListInfo::decode_new(&encoded[..]).unwrap(), info);
+
+        // An entry running past the LIST is still an error
+        encoded[28..32].copy_from_slice(&100u32.to_le_bytes());
+        assert!(matches!(
+            ListInfo::decode_new(&encoded[..]),
+            Err(Error::TruncatedChunk(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_other_lists() {
+        let adtl = b"LIST\x04\0\0\0adtl";
+
+        assert!(matches!(
+            ListInfo::decode_new(&adtl[..]),
+            Err(Error::BadMagic { .. })
+        ));
+        assert!(!ListInfo::is_info(&RawChunk::new(
+            FourCC::new(*LIST_TYPE),
+            b"adtl".to_vec()
+        )));
+    }
+}
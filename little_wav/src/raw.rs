@@ -0,0 +1,85 @@
+use crate::{
+    container::Container,
+    error::Error,
+    header_format::{Encoding, HeaderFormat},
+    sample::{swap_bytes, ByteOrder, Sample},
+    wav::Wav,
+};
+use std::io::Read;
+
+// Headerless PCM, the caller knows the format. 8-bit samples are unsigned like in WAV.
+
+pub(crate) fn decode<R: Read, T: Sample>(
+    mut reader: R,
+    format: HeaderFormat,
+    byte_order: ByteOrder,
+) -> Result<Wav<T>, Error> {
+    // The format comes from the caller, not from a header that was checked
+    format.check()?;
+
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body)?;
+    to_le(&mut body, &format, byte_order);
+
+    Wav::from_parts(Container::Wave, format, None, Vec::new(), &body)
+}
+
+pub(crate) fn encode<T: Sample>(wav: &Wav<T>, byte_order: ByteOrder) -> Result<Vec<u8>, Error> {
+    let mut body = wav.encode_body()?;
+    to_le(&mut body, wav.format(), byte_order);
+
+    Ok(body)
+}
+
+// Swaps between the given byte order and little-endian, it works the same both ways
+fn to_le(bytes: &mut [u8], format: &HeaderFormat, byte_order: ByteOrder) {
+    let companded = matches!(format.get_encoding(), Encoding::ALAW | Encoding::MULAW);
+    if byte_order == ByteOrder::BigEndian && !companded {
+        swap_bytes(bytes, format.get_sample_size());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sample::I24;
+
+    #[test]
+    fn big_endian_round_trip() {
+        let format = HeaderFormat::new(Encoding::LPCM, 2, 48_000, 3);
+        let bytes = [0x01, 0x02, 0x03, 0xff, 0xff, 0xfe];
+
+        let wav = Wav::<I24>::decode_raw(&bytes[..], format.clone(), ByteOrder::BigEndian).unwrap();
+        assert_eq!(wav.format(), &format);
+        assert_eq!(wav.body(), [I24::new(0x010203), I24::new(-2)]);
+
+        assert_eq!(wav.encode_raw(ByteOrder::BigEndian).unwrap(), bytes);
+        assert_eq!(
+            wav.encode_raw(ByteOrder::LittleEndian).unwrap(),
+            [0x03, 0x02, 0x01, 0xfe, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn checks_format() {
+        let mulaw = HeaderFormat::new(Encoding::MULAW, 1, 8_000, 1);
+        let wav = Wav::<i16>::decode_raw(&[0xff, 0x7f][..], mulaw, ByteOrder::BigEndian).unwrap();
+        assert_eq!(wav.body(), [0, 0]);
+
+        let pcm = HeaderFormat::new(Encoding::LPCM, 1, 8_000, 2);
+        assert!(matches!(
+            Wav::<i16>::decode_raw(&[0; 3][..], pcm.clone(), ByteOrder::LittleEndian),
+            Err(Error::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            Wav::<f32>::decode_raw(&[0; 4][..], pcm, ByteOrder::LittleEndian),
+            Err(Error::IncompatibleSample { .. })
+        ));
+
+        let empty = HeaderFormat::new(Encoding::LPCM, 1, 8_000, 0);
+        assert!(matches!(
+            Wav::<i16>::decode_raw(&[0; 4][..], empty, ByteOrder::BigEndian),
+            Err(Error::InvalidFormat { .. })
+        ));
+    }
+}
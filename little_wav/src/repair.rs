@@ -0,0 +1,375 @@
+use crate::{
+    chunk::padded,
+    codable::{read_exact, Decodable},
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+    header_ds64::{HeaderDs64, DS64_TYPE},
+    header_fact::FACT_TYPE,
+    header_format::{HeaderFormat, FMT_TYPE},
+    header_riff::{HeaderRiff, RIFF_ID},
+};
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+// A header field set straight by repair
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fix {
+    RiffSize { from: u64, to: u64 },
+    DataSize { from: u64, to: u64 },
+    // Frames counted in fact or ds64
+    FrameCount { from: u64, to: u64 },
+    // Bytes of an unfinished frame cut off the end of data
+    PartialFrame(u64),
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::RiffSize { from, to } => write!(f, "RIFF size {} -> {}", from, to),
+            Fix::DataSize { from, to } => write!(f, "data size {} -> {}", from, to),
+            Fix::FrameCount { from, to } => write!(f, "frame count {} -> {}", from, to),
+            Fix::PartialFrame(bytes) => write!(f, "dropped {} bytes of a partial frame", bytes),
+        }
+    }
+}
+
+// What has to be written where for the header to match the file
+pub(crate) struct Plan {
+    patches: Vec<(u64, Vec<u8>)>,
+    len: u64,
+    pub(crate) fixes: Vec<Fix>,
+}
+
+impl Plan {
+    pub(crate) fn apply(&self, bytes: &mut Vec<u8>) {
+        for (at, patch) in &self.patches {
+            let at = *at as usize;
+            bytes[at..at + patch.len()].copy_from_slice(patch);
+        }
+        bytes.resize(self.len as usize, 0);
+    }
+}
+
+// Fixes the sizes of a RIFF/WAVE file in place, as left by a recorder that lost power.
+// Sizes are worked out from the file length, a partial frame at the end is cut off.
+pub fn repair_file(path: &str) -> Result<Vec<Fix>, Error> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let plan = plan(&mut file)?;
+
+    for (at, patch) in &plan.patches {
+        file.seek(SeekFrom::Start(*at))?;
+        file.write_all(patch)?;
+    }
+    file.set_len(plan.len)?;
+
+    Ok(plan.fixes)
+}
+
+// Reads chunk headers only, the samples are never loaded
+pub(crate) fn plan<R: Read + Seek>(reader: &mut R) -> Result<Plan, Error> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let riff = HeaderRiff::decode_new(&mut *reader)?;
+    let wide = riff.get_form().is_64_bit();
+
+    let mut ds64: Option<HeaderDs64> = None;
+    let mut format: Option<HeaderFormat> = None;
+    let mut fact_at = None;
+    let mut data = None;
+    let mut position: u64 = 12;
+    while data.is_none() && position.saturating_add(8) <= len {
+        let (id, size) = read_chunk_header(reader, position, ds64.as_ref())?;
+        reader.seek(SeekFrom::Start(position))?;
+        // Header decoders read the chunk header too
+        let body = reader.by_ref().take(8u64.saturating_add(size));
+        match id.as_array() {
+            DS64_TYPE if position == 12 => ds64 = Some(HeaderDs64::decode_new(body)?),
+            FMT_TYPE if format.is_none() => format = Some(HeaderFormat::decode_new(body)?),
+            FACT_TYPE => fact_at = Some(position),
+            DATA_TYPE => data = Some((position, size)),
+            _ => {}
+        }
+        position = position.saturating_add(padded(size).saturating_add(8));
+    }
+
+    let format = format.ok_or(Error::MissingChunk(FourCC::new(*FMT_TYPE)))?;
+    let (data_at, declared) = data.ok_or(Error::MissingChunk(FourCC::new(*DATA_TYPE)))?;
+    let ds64 = match (wide, ds64) {
+        (true, None) => return Err(Error::MissingChunk(FourCC::new(*DS64_TYPE))),
+        (_, ds64) => ds64,
+    };
+
+    // A data size that fits and is followed by a chunk or the end of the file is trusted,
+    // anything else means data runs to the end
+    let body_start = data_at + 8;
+    let available = len - body_start;
+    let fits = declared <= available
+        && (padded(declared) == available
+            || is_chunk_at(reader, body_start + padded(declared), len, ds64.as_ref())?);
+    let last = !fits || padded(declared) == available;
+
+    let mut size = if fits { declared } else { available };
+    let mut partial = 0;
+    if last {
+        partial = size % (format.get_block_align() as u64).max(1);
+        size -= partial;
+    }
+    let end = if last {
+        body_start + padded(size)
+    } else {
+        end_of_chunks(reader, body_start + padded(size), len, ds64.as_ref())?
+    };
+
+    let mut fixes = Vec::new();
+    if size != declared {
+        fixes.push(Fix::DataSize {
+            from: declared,
+            to: size,
+        });
+    }
+    if partial > 0 {
+        fixes.push(Fix::PartialFrame(partial));
+    }
+    let frames = size / (format.get_block_align() as u64).max(1);
+
+    let mut patches = Vec::new();
+    if size != declared && !wide {
+        let size = u32::try_from(size).map_err(|_| Error::SizeMismatch {
+            id: FourCC::new(*DATA_TYPE),
+            expected: u32::MAX as u64,
+            found: size,
+        })?;
+        patches.push((data_at + 4, size.to_le_bytes().to_vec()));
+    }
+
+    match &ds64 {
+        // RF64 keeps its sizes in ds64 at 12, the RIFF and data headers say 0xFFFFFFFF
+        Some(ds64) => {
+            let mut buffer_16 = [0; 4];
+            reader.seek(SeekFrom::Start(data_at + 4))?;
+            read_exact(reader, &mut buffer_16, DATA_TYPE)?;
+            if u32::from_le_bytes(buffer_16) != u32::MAX {
+                patches.push((data_at + 4, u32::MAX.to_le_bytes().to_vec()));
+            }
+            let fields = [
+                (20, ds64.get_riff_size(), end - 8),
+                (
+                    28,
+                    ds64.get_chunk_size(FourCC::new(*DATA_TYPE)).unwrap_or(0),
+                    size,
+                ),
+                (36, ds64.get_sample_count(), frames),
+            ];
+            for (at, from, to) in fields {
+                if from != to {
+                    patches.push((at, to.to_le_bytes().to_vec()));
+                }
+            }
+            if ds64.get_riff_size() != end - 8 {
+                fixes.push(Fix::RiffSize {
+                    from: ds64.get_riff_size(),
+                    to: end - 8,
+                });
+            }
+            if ds64.get_sample_count() != frames {
+                fixes.push(Fix::FrameCount {
+                    from: ds64.get_sample_count(),
+                    to: frames,
+                });
+            }
+        }
+        None => {
+            let riff_size = riff.get_size();
+            if riff_size != end - 8 {
+                let to = u32::try_from(end - 8).map_err(|_| Error::SizeMismatch {
+                    id: FourCC::new(*RIFF_ID),
+                    expected: u32::MAX as u64,
+                    found: end - 8,
+                })?;
+                patches.push((4, to.to_le_bytes().to_vec()));
+                fixes.push(Fix::RiffSize {
+                    from: riff_size,
+                    to: end - 8,
+                });
+            }
+        }
+    }
+
+    if let Some(fact_at) = fact_at {
+        let mut buffer_16 = [0; 4];
+        reader.seek(SeekFrom::Start(fact_at + 8))?;
+        read_exact(reader, &mut buffer_16, FACT_TYPE)?;
+        let counted = u32::from_le_bytes(buffer_16);
+        // 0xFFFFFFFF defers to ds64
+        if counted != u32::MAX && counted as u64 != frames {
+            let to = u32::try_from(frames).unwrap_or(u32::MAX);
+            patches.push((fact_at + 8, to.to_le_bytes().to_vec()));
+            fixes.push(Fix::FrameCount {
+                from: counted as u64,
+                to: to as u64,
+            });
+        }
+    }
+
+    Ok(Plan {
+        patches,
+        // Stray bytes after the last chunk are left alone
+        len: if last { end } else { len },
+        fixes,
+    })
+}
+
+fn read_chunk_header<R: Read + Seek>(
+    reader: &mut R,
+    position: u64,
+    ds64: Option<&HeaderDs64>,
+) -> Result<(FourCC, u64), Error> {
+    let mut buffer = [0; 8];
+    reader.seek(SeekFrom::Start(position))?;
+    read_exact(reader, &mut buffer, RIFF_ID)?;
+
+    let id = FourCC::new(buffer[..4].try_into().expect("4 bytes"));
+    let size = match (
+        ds64,
+        u32::from_le_bytes(buffer[4..].try_into().expect("4 bytes")),
+    ) {
+        (Some(ds64), u32::MAX) => ds64.get_chunk_size(id).unwrap_or(u32::MAX as u64),
+        (_, size) => size as u64,
+    };
+
+    Ok((id, size))
+}
+
+// Whether a whole chunk starts at `position`
+fn is_chunk_at<R: Read + Seek>(
+    reader: &mut R,
+    position: u64,
+    len: u64,
+    ds64: Option<&HeaderDs64>,
+) -> Result<bool, Error> {
+    if position.saturating_add(8) > len {
+        return Ok(false);
+    }
+    let (id, size) = read_chunk_header(reader, position, ds64)?;
+
+    Ok(id.as_array().iter().all(|byte| (0x20..0x7f).contains(byte))
+        && position.saturating_add(size.saturating_add(8)) <= len)
+}
+
+// End of the last whole chunk from `position` on, stray bytes after it don't count
+fn end_of_chunks<R: Read + Seek>(
+    reader: &mut R,
+    mut position: u64,
+    len: u64,
+    ds64: Option<&HeaderDs64>,
+) -> Result<u64, Error> {
+    while is_chunk_at(reader, position, len, ds64)? {
+        let (_, size) = read_chunk_header(reader, position, ds64)?;
+        position = position.saturating_add(padded(size).saturating_add(8));
+    }
+
+    Ok(position.min(len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codable::Encodable, header_ds64::test::oversized_junk, header_format::Encoding,
+        header_riff::RiffForm, wav::Wav,
+    };
+    use std::fs;
+
+    fn ramp(form: RiffForm, channels: usize) -> Wav<i16> {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, channels, 8_000);
+        wav.set_form(form);
+        wav.push_body((0..8).collect());
+        wav
+    }
+
+    #[test]
+    fn recovers_zeroed_sizes() {
+        let wav = ramp(RiffForm::Riff, 2);
+        let mut bytes = wav.encode().unwrap();
+        bytes[4..8].fill(0);
+        bytes[40..44].fill(0);
+        // Half a frame written as the power went
+        bytes.extend_from_slice(&[1, 2, 3]);
+
+        let (repaired, fixes) = Wav::<i16>::decode_lenient(&bytes[..]).unwrap();
+        assert_eq!(repaired.body(), wav.body());
+        assert_eq!(
+            fixes,
+            [
+                Fix::DataSize { from: 0, to: 16 },
+                Fix::PartialFrame(3),
+                Fix::RiffSize { from: 0, to: 52 },
+            ]
+        );
+        assert_eq!(fixes[1].to_string(), "dropped 3 bytes of a partial frame");
+
+        let (_, fixes) = Wav::<i16>::decode_lenient(&wav.encode().unwrap()[..]).unwrap();
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn repairs_rf64_in_place() {
+        const FILE_PATH_OUT: &str = "test_assets/_repair_out.wav";
+        let expected = ramp(RiffForm::Rf64, 1).encode().unwrap();
+        let mut bytes = expected.clone();
+        // ds64 riff, data and sample count, and the data header size
+        bytes[20..44].fill(0);
+        let data_at = bytes.windows(4).position(|id| id == DATA_TYPE).unwrap();
+        bytes[data_at + 4..data_at + 8].fill(0);
+        bytes.push(7);
+        let (repaired, _) = Wav::<i16>::decode_lenient(&bytes[..]).unwrap();
+        assert_eq!(repaired.body(), ramp(RiffForm::Rf64, 1).body());
+        fs::write(FILE_PATH_OUT, &bytes).unwrap();
+
+        let fixes = repair_file(FILE_PATH_OUT).unwrap();
+        let repaired = fs::read(FILE_PATH_OUT).unwrap();
+        let again = repair_file(FILE_PATH_OUT).unwrap();
+        fs::remove_file(FILE_PATH_OUT).unwrap();
+
+        assert_eq!(
+            fixes,
+            [
+                Fix::DataSize { from: 0, to: 16 },
+                Fix::PartialFrame(1),
+                Fix::RiffSize {
+                    from: 0,
+                    to: expected.len() as u64 - 8
+                },
+                Fix::FrameCount { from: 0, to: 8 },
+            ]
+        );
+        assert_eq!(repaired, expected);
+        assert!(again.is_empty());
+    }
+
+    #[test]
+    fn keeps_chunks_after_data() {
+        let wav = ramp(RiffForm::Riff, 2);
+        let mut bytes = wav.encode().unwrap();
+        // Appended without updating RIFF, plus a stray byte
+        bytes.extend_from_slice(b"note\x03\0\0\0abc\0");
+        bytes.push(0xff);
+
+        let (repaired, fixes) = Wav::<i16>::decode_lenient(&bytes[..]).unwrap();
+        assert_eq!(repaired.body(), wav.body());
+        assert_eq!(fixes, [Fix::RiffSize { from: 52, to: 64 }]);
+    }
+
+    #[test]
+    fn survives_oversized_table_entry() {
+        // JUNK runs past the end of the file, the walk stops there
+        assert!(matches!(
+            Wav::<i16>::decode_lenient(&oversized_junk()[..]),
+            Err(Error::MissingChunk(_))
+        ));
+    }
+}
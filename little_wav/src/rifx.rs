@@ -0,0 +1,265 @@
+use crate::{
+    bext::BEXT_TYPE,
+    chunk::padded,
+    container::Container,
+    cue::{ADTL_TYPE, CUE_TYPE, LTXT_TYPE},
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+    header_fact::FACT_TYPE,
+    header_format::FMT_TYPE,
+    list_info::LIST_TYPE,
+    sample::{swap_bytes, ByteOrder, Sample},
+    sampler::SMPL_TYPE,
+    wav::Wav,
+};
+use std::io::Read;
+
+// RIFX is RIFF with every number big-endian: chunk sizes, chunk fields, and samples.
+// Files are swapped to RIFF layout as a whole and go through the RIFF code from there.
+
+pub(crate) const RIFX_ID: &[u8; 4] = b"RIFX";
+const RIFF_ID: &[u8; 4] = b"RIFF";
+
+// Field widths of the fmt chunk, up to the integer fields of the extensible sub format GUID
+const FMT_FIELDS: [usize; 12] = [2, 2, 4, 4, 2, 2, 2, 2, 4, 4, 2, 2];
+
+pub(crate) fn decode<R: Read, T: Sample>(mut reader: R) -> Result<Wav<T>, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    swap_file(&mut bytes, ByteOrder::BigEndian);
+
+    let mut wav = Wav::decode_riff(&bytes[..])?;
+    wav.set_container(Container::Rifx);
+
+    Ok(wav)
+}
+
+pub(crate) fn encode<T: Sample>(wav: &Wav<T>) -> Result<Vec<u8>, Error> {
+    // No 64-bit flavour of RIFX to fall back on
+    if wav.form().is_64_bit() {
+        return Err(Error::SizeMismatch {
+            id: FourCC::new(*RIFX_ID),
+            expected: u32::MAX as u64,
+            found: wav.get_riff_size(),
+        });
+    }
+
+    let mut bytes = wav.encode_riff()?;
+    swap_file(&mut bytes, ByteOrder::LittleEndian);
+
+    Ok(bytes)
+}
+
+// Turns a RIFF file into RIFX or the other way round, `from` being the current order.
+// Unknown chunks are left alone since their layout is anybody's guess.
+fn swap_file(bytes: &mut [u8], from: ByteOrder) {
+    if bytes.len() < 12 {
+        return;
+    }
+    bytes[..4].copy_from_slice(match from {
+        ByteOrder::BigEndian => RIFF_ID,
+        ByteOrder::LittleEndian => RIFX_ID,
+    });
+    bytes[4..8].reverse();
+
+    let mut sample_size = 0;
+    let mut position = 12;
+    while let Some(size) = u32_in(bytes, position + 4, from) {
+        let size = size as u64;
+        bytes[position + 4..position + 8].reverse();
+
+        let id: [u8; 4] = bytes[position..position + 4].try_into().expect("4 bytes");
+        let start = position + 8;
+        let end = (start as u64 + size).min(bytes.len() as u64) as usize;
+        let body = &mut bytes[start..end];
+
+        match &id {
+            FMT_TYPE => {
+                // Sample size is read off the little-endian side of the swap
+                if from == ByteOrder::BigEndian {
+                    swap_fields(body, &FMT_FIELDS);
+                }
+                if body.len() >= 16 {
+                    sample_size = (u16::from_le_bytes([body[14], body[15]]) as usize).div_ceil(8);
+                }
+                if from == ByteOrder::LittleEndian {
+                    swap_fields(body, &FMT_FIELDS);
+                }
+            }
+            FACT_TYPE => swap_fields(body, &[4]),
+            DATA_TYPE if sample_size > 1 => swap_bytes(body, sample_size),
+            LIST_TYPE => swap_list(body, from),
+            CUE_TYPE => swap_cue(body, from),
+            SMPL_TYPE => swap_smpl(body, from),
+            BEXT_TYPE => swap_bext(body),
+            _ => {}
+        }
+
+        position = start + padded(size) as usize;
+    }
+}
+
+// Sub-chunk sizes of a LIST, after its 4 byte list type, and the fields of adtl entries
+fn swap_list(body: &mut [u8], from: ByteOrder) {
+    let adtl = body.get(..4) == Some(&ADTL_TYPE[..]);
+    let mut position = 4;
+    while let Some(size) = u32_in(body, position + 4, from) {
+        let size = size as u64;
+        body[position + 4..position + 8].reverse();
+
+        let start = position + 8;
+        let end = (start as u64 + size).min(body.len() as u64) as usize;
+        if adtl {
+            // Cue point id, for ltxt also the length and the four fields after the purpose
+            let is_ltxt = body[position..position + 4] == *LTXT_TYPE;
+            let entry = &mut body[start..end];
+            swap_fields(entry, &[4]);
+            if is_ltxt {
+                swap_fields(entry.get_mut(4..).unwrap_or_default(), &[4]);
+                swap_fields(entry.get_mut(12..).unwrap_or_default(), &[2; 4]);
+            }
+        }
+
+        position = start + padded(size) as usize;
+    }
+}
+
+// Point count, then points of six fields, the third being a chunk id
+fn swap_cue(body: &mut [u8], from: ByteOrder) {
+    let Some(count) = u32_in(body, 0, from) else {
+        return;
+    };
+    body[..4].reverse();
+    for point in body[4..].chunks_exact_mut(24).take(count as usize) {
+        swap_fields(&mut point[..8], &[4, 4]);
+        swap_fields(&mut point[12..], &[4, 4, 4]);
+    }
+}
+
+// Nine header fields and loops of six, the sampler specific data is left alone
+fn swap_smpl(body: &mut [u8], from: ByteOrder) {
+    let Some(loop_count) = u32_in(body, 28, from) else {
+        return;
+    };
+    swap_fields(body, &[4; 9]);
+    let loops = body.get_mut(36..).unwrap_or_default();
+    for sample_loop in loops.chunks_exact_mut(24).take(loop_count as usize) {
+        swap_fields(sample_loop, &[4; 6]);
+    }
+}
+
+// Time reference, version and loudness, the rest being text and UMID bytes
+fn swap_bext(body: &mut [u8]) {
+    if body.len() >= 422 {
+        swap_fields(&mut body[338..], &[8, 2]);
+        swap_fields(&mut body[412..], &[2; 5]);
+    }
+}
+
+// The u32 at `at`, read in the order it's stored in
+fn u32_in(bytes: &[u8], at: usize, order: ByteOrder) -> Option<u32> {
+    let field = bytes.get(at..at + 4)?.try_into().expect("4 bytes");
+    Some(match order {
+        ByteOrder::BigEndian => u32::from_be_bytes(field),
+        ByteOrder::LittleEndian => u32::from_le_bytes(field),
+    })
+}
+
+// Reverses each field in turn, stopping at the end of the body
+fn swap_fields(body: &mut [u8], fields: &[usize]) {
+    let mut position = 0;
+    for width in fields {
+        if position + width > body.len() {
+            break;
+        }
+        body[position..position + width].reverse();
+        position += width;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        channel_mask::ChannelMask,
+        codable::{Decodable, Encodable},
+        cue::Marker,
+        header_format::Encoding,
+        sample::I24,
+        sampler::{Loop, LoopKind, Sampler},
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut wav = Wav::<I24>::new(Encoding::LPCM, 2, 48_000);
+        wav.set_container(Container::Rifx);
+        wav.set_channel_mask(ChannelMask::STEREO);
+        wav.push_body(vec![I24::new(0x010203), I24::new(-2)]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(encoded[..12], *b"RIFX\0\0\0\x42WAVE");
+        // fmt size, tag and channels
+        assert_eq!(encoded[16..24], [0, 0, 0, 40, 0xff, 0xfe, 0, 2]);
+        assert_eq!(encoded[encoded.len() - 6..], [1, 2, 3, 0xff, 0xff, 0xfe]);
+
+        let decoded = Wav::<I24>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.container(), Container::Rifx);
+        assert_eq!(decoded.format(), wav.format());
+        assert_eq!(decoded.body(), wav.body());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn reads_as_riff() {
+        let mut wav = Wav::<i16>::new(Encoding::MULAW, 1, 8_000);
+        wav.set_container(Container::Rifx);
+        wav.set_title("Tone").unwrap();
+        wav.push_body(vec![100, -100, 1_000]);
+
+        let encoded = wav.encode().unwrap();
+        // LIST and INFO entry sizes, before data and its 3 padded samples
+        let list = encoded.len() - 4 - 8 - 18 - 8;
+        assert_eq!(encoded[list..list + 8], *b"LIST\0\0\0\x12");
+        assert_eq!(encoded[list + 12..list + 20], *b"INAM\0\0\0\x05");
+
+        let mut decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.info(), wav.info());
+        decoded.set_container(Container::Wave);
+        wav.set_container(Container::Wave);
+
+        assert_eq!(decoded.encode().unwrap(), wav.encode().unwrap());
+    }
+
+    #[test]
+    fn swaps_metadata() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 48_000);
+        wav.set_container(Container::Rifx);
+        let mut verse = Marker::region(2, 1_000, 500);
+        verse.label = Some("Verse".to_string());
+        wav.set_markers(vec![Marker::new(1, 10), verse]).unwrap();
+        let mut sampler = Sampler::new(48_000);
+        sampler.loops.push(Loop::new(LoopKind::Forward, 100, 199));
+        wav.set_sampler(sampler.clone());
+        wav.set_time_reference(0x0102_0304_0506).unwrap();
+        wav.push_body(vec![1, 2, 3]);
+
+        let encoded = wav.encode().unwrap();
+        let cue = encoded.windows(4).position(|id| id == CUE_TYPE).unwrap();
+        // Size, point count, then the first point's id
+        assert_eq!(
+            encoded[cue + 4..cue + 16],
+            [0, 0, 0, 52, 0, 0, 0, 2, 0, 0, 0, 1]
+        );
+
+        let mut decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.markers(), wav.markers());
+        assert_eq!(decoded.sampler(), Some(sampler));
+        assert_eq!(decoded.time_reference(), Some(0x0102_0304_0506));
+        assert_eq!(decoded.encode().unwrap(), encoded);
+
+        decoded.set_container(Container::Wave);
+        wav.set_container(Container::Wave);
+        assert_eq!(decoded.encode().unwrap(), wav.encode().unwrap());
+    }
+}
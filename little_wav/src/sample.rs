@@ -0,0 +1,269 @@
+use crate::{error::Error, header_format::Encoding};
+use std::fmt::Debug;
+
+// Sample formats a WAV body can be made of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    U8,
+    I16,
+    I24,
+    I32,
+    F32,
+    F64,
+}
+
+impl SampleFormat {
+    // Picks the sample format stored as `sample_size` bytes with the given encoding
+    pub fn from_encoding(encoding: Encoding, sample_size: usize) -> Result<Self, Error> {
+        match (encoding, sample_size) {
+            (Encoding::LPCM, 1) => Ok(SampleFormat::U8),
+            (Encoding::LPCM, 2) => Ok(SampleFormat::I16),
+            (Encoding::LPCM, 3) => Ok(SampleFormat::I24),
+            (Encoding::LPCM, 4) => Ok(SampleFormat::I32),
+            // Companded samples are expanded to linear 16 bits
+            (Encoding::ALAW | Encoding::MULAW, 1) => Ok(SampleFormat::I16),
+            (Encoding::IEEE, 4) => Ok(SampleFormat::F32),
+            (Encoding::IEEE, 8) => Ok(SampleFormat::F64),
+            _ => Err(Error::UnsupportedSampleFormat {
+                encoding,
+                bits_per_sample: (sample_size * 8) as u16,
+            }),
+        }
+    }
+
+    // Bytes taken by one sample on disk
+    pub fn get_sample_size(self) -> usize {
+        match self {
+            SampleFormat::U8 => 1,
+            SampleFormat::I16 => 2,
+            SampleFormat::I24 => 3,
+            SampleFormat::I32 | SampleFormat::F32 => 4,
+            SampleFormat::F64 => 8,
+        }
+    }
+    pub fn get_bits_per_sample(self) -> u16 {
+        self.get_sample_size() as u16 * 8
+    }
+    pub fn is_float(self) -> bool {
+        matches!(self, SampleFormat::F32 | SampleFormat::F64)
+    }
+    pub fn get_encoding(self) -> Encoding {
+        match self {
+            SampleFormat::F32 | SampleFormat::F64 => Encoding::IEEE,
+            _ => Encoding::LPCM,
+        }
+    }
+}
+
+// Order of the bytes of sizes and samples on disk. WAV is little-endian, AIFF big-endian.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
+// In-memory sample type of a Wav, knows its own on-disk little-endian layout
+pub trait Sample: Copy + Default + PartialEq + Debug {
+    const FORMAT: SampleFormat;
+
+    // `bytes` holds exactly FORMAT.get_sample_size() bytes
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+    fn write_le_bytes(self, out: &mut Vec<u8>);
+
+    // Full scale is -1.0..1.0, integers are divided by 2^(bits - 1)
+    fn to_f64(self) -> f64;
+    // Integers round to nearest and clip at both ends of their range, floats pass through
+    fn from_f64(value: f64) -> Self;
+}
+
+// Scales a full scale value to a signed integer of `bits` bits, clipping out of range values
+fn scale_to_int(value: f64, bits: u32) -> f64 {
+    let scale = (1u64 << (bits - 1)) as f64;
+    (value * scale).round().clamp(-scale, scale - 1.0)
+}
+
+// Signed 24-bit sample, stored as 3 packed bytes on disk
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct I24(i32);
+
+impl I24 {
+    pub const MIN: I24 = I24(-(1 << 23));
+    pub const MAX: I24 = I24((1 << 23) - 1);
+
+    // Values out of the 24-bit range saturate
+    pub fn new(value: i32) -> Self {
+        Self(value.clamp(Self::MIN.0, Self::MAX.0))
+    }
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl From<I24> for i32 {
+    fn from(sample: I24) -> Self {
+        sample.get()
+    }
+}
+
+impl Sample for u8 {
+    const FORMAT: SampleFormat = SampleFormat::U8;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+    fn to_f64(self) -> f64 {
+        (self as f64 - 128.0) / 128.0
+    }
+    fn from_f64(value: f64) -> Self {
+        // Unsigned 8 bits sits on a 128 offset
+        (scale_to_int(value, 8) + 128.0) as u8
+    }
+}
+
+impl Sample for i16 {
+    const FORMAT: SampleFormat = SampleFormat::I16;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        i16::from_le_bytes([bytes[0], bytes[1]])
+    }
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn to_f64(self) -> f64 {
+        self as f64 / 32_768.0
+    }
+    fn from_f64(value: f64) -> Self {
+        scale_to_int(value, 16) as i16
+    }
+}
+
+impl Sample for I24 {
+    const FORMAT: SampleFormat = SampleFormat::I24;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        // Put the 3 bytes on top of an i32 and shift back down to sign-extend
+        I24(i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8)
+    }
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0.to_le_bytes()[..3]);
+    }
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / 8_388_608.0
+    }
+    fn from_f64(value: f64) -> Self {
+        I24(scale_to_int(value, 24) as i32)
+    }
+}
+
+impl Sample for i32 {
+    const FORMAT: SampleFormat = SampleFormat::I32;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn to_f64(self) -> f64 {
+        self as f64 / 2_147_483_648.0
+    }
+    fn from_f64(value: f64) -> Self {
+        scale_to_int(value, 32) as i32
+    }
+}
+
+impl Sample for f32 {
+    const FORMAT: SampleFormat = SampleFormat::F32;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl Sample for f64 {
+    const FORMAT: SampleFormat = SampleFormat::F64;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(bytes);
+        f64::from_le_bytes(buffer)
+    }
+    fn write_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+// Decodes packed little-endian samples, a trailing partial sample is ignored
+pub(crate) fn decode_samples<T: Sample>(bytes: &[u8]) -> Vec<T> {
+    bytes
+        .chunks_exact(T::FORMAT.get_sample_size())
+        .map(T::from_le_bytes)
+        .collect()
+}
+
+pub(crate) fn encode_samples<T: Sample>(samples: &[T], out: &mut Vec<u8>) {
+    out.reserve(samples.len() * T::FORMAT.get_sample_size());
+    for sample in samples {
+        sample.write_le_bytes(out);
+    }
+}
+
+// Turns packed samples of `sample_size` bytes from one byte order to the other, in place
+pub(crate) fn swap_bytes(bytes: &mut [u8], sample_size: usize) {
+    for sample in bytes.chunks_exact_mut(sample_size) {
+        sample.reverse();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn i24_round_trip() {
+        let samples = [I24::MIN, I24::new(-1), I24::new(0x123456), I24::MAX];
+
+        let mut bytes = Vec::new();
+        encode_samples(&samples, &mut bytes);
+
+        assert_eq!(
+            bytes,
+            [0, 0, 0x80, 0xff, 0xff, 0xff, 0x56, 0x34, 0x12, 0xff, 0xff, 0x7f]
+        );
+        assert_eq!(decode_samples::<I24>(&bytes), samples);
+    }
+
+    #[test]
+    fn i24_saturates() {
+        assert_eq!(I24::new(1 << 24), I24::MAX);
+        assert_eq!(I24::new(-(1 << 24)).get(), -(1 << 23));
+    }
+
+    #[test]
+    fn format_from_encoding() {
+        let format = SampleFormat::from_encoding(Encoding::LPCM, 3).unwrap();
+        assert_eq!(format, SampleFormat::I24);
+        assert_eq!(format.get_bits_per_sample(), 24);
+
+        assert!(SampleFormat::from_encoding(Encoding::IEEE, 2).is_err());
+    }
+}
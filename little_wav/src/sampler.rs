@@ -0,0 +1,252 @@
+use crate::{chunk::RawChunk, cue::u32_at, error::Error, four_cc::FourCC};
+use std::ops::Range;
+
+pub(crate) const SMPL_TYPE: &[u8; 4] = b"smpl";
+
+// Fixed part of smpl, loops then sampler specific data follow
+const HEADER_SIZE: usize = 36;
+const LOOP_SIZE: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopKind {
+    Forward,
+    // Forward then backward, over and over
+    PingPong,
+    Reverse,
+    // Manufacturer specific, 32 and above
+    Other(u32),
+}
+
+impl LoopKind {
+    fn from_u32(kind: u32) -> Self {
+        match kind {
+            0 => LoopKind::Forward,
+            1 => LoopKind::PingPong,
+            2 => LoopKind::Reverse,
+            kind => LoopKind::Other(kind),
+        }
+    }
+    fn to_u32(self) -> u32 {
+        match self {
+            LoopKind::Forward => 0,
+            LoopKind::PingPong => 1,
+            LoopKind::Reverse => 2,
+            LoopKind::Other(kind) => kind,
+        }
+    }
+}
+
+// Loop range in frames, both ends played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Loop {
+    // Cue point naming the loop, 0 when there's none
+    pub id: u32,
+    pub kind: LoopKind,
+    pub start: u32,
+    pub end: u32,
+    // Fraction of a frame past `end`, in 1/2^32ths
+    pub fraction: u32,
+    // 0 loops forever
+    pub play_count: u32,
+}
+
+impl Loop {
+    pub fn new(kind: LoopKind, start: u32, end: u32) -> Self {
+        Self {
+            id: 0,
+            kind,
+            start,
+            end,
+            fraction: 0,
+            play_count: 0,
+        }
+    }
+
+    // Where the loop ends up once `removed` frames are cut out, None if the cut runs through it.
+    // A loop ending before it starts has no place to move to and is dropped too.
+    pub(crate) fn cut(&self, removed: &Range<u32>) -> Option<Self> {
+        let count = removed.end - removed.start;
+
+        if self.start > self.end {
+            None
+        } else if self.end < removed.start {
+            Some(*self)
+        } else if self.start >= removed.end {
+            Some(Self {
+                start: self.start - count,
+                end: self.end - count,
+                ..*self
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// Sampler chunk: how an instrument plays the file, root note and loops
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sampler {
+    pub manufacturer: u32,
+    pub product: u32,
+    // Nanoseconds per frame
+    pub sample_period: u32,
+    // MIDI note played back at the recorded pitch, and how many 1/2^32ths of a semitone above
+    pub unity_note: u32,
+    pub pitch_fraction: u32,
+    pub smpte_format: u32,
+    pub smpte_offset: u32,
+    pub loops: Vec<Loop>,
+    // Manufacturer specific bytes after the loops
+    pub sampler_data: Vec<u8>,
+}
+
+impl Sampler {
+    // Middle C as root note, no loops
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            manufacturer: 0,
+            product: 0,
+            sample_period: sample_period(sample_rate),
+            unity_note: 60,
+            pitch_fraction: 0,
+            smpte_format: 0,
+            smpte_offset: 0,
+            loops: Vec::new(),
+            sampler_data: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_smpl(chunk: &RawChunk) -> bool {
+        chunk.id() == FourCC::new(*SMPL_TYPE)
+    }
+
+    pub(crate) fn from_chunk(chunk: &RawChunk) -> Result<Self, Error> {
+        let data = chunk.data();
+        let truncated = || Error::TruncatedChunk(FourCC::new(*SMPL_TYPE));
+        let field = |index: usize| u32_at(data, index * 4).ok_or_else(truncated);
+
+        let loop_count = field(7)? as usize;
+        let sampler_data_size = field(8)? as usize;
+        let loops_end = HEADER_SIZE + loop_count * LOOP_SIZE;
+        if data.len() < loops_end {
+            return Err(truncated());
+        }
+
+        let loops = data[HEADER_SIZE..loops_end]
+            .chunks_exact(LOOP_SIZE)
+            .map(|bytes| {
+                let field = |index: usize| u32_at(bytes, index * 4).expect("loop is 24 bytes");
+                Loop {
+                    id: field(0),
+                    kind: LoopKind::from_u32(field(1)),
+                    start: field(2),
+                    end: field(3),
+                    fraction: field(4),
+                    play_count: field(5),
+                }
+            })
+            .collect();
+        let sampler_data = data
+            .get(loops_end..loops_end + sampler_data_size)
+            .ok_or_else(truncated)?
+            .to_vec();
+
+        Ok(Self {
+            manufacturer: field(0)?,
+            product: field(1)?,
+            sample_period: field(2)?,
+            unity_note: field(3)?,
+            pitch_fraction: field(4)?,
+            smpte_format: field(5)?,
+            smpte_offset: field(6)?,
+            loops,
+            sampler_data,
+        })
+    }
+    pub(crate) fn to_chunk(&self) -> RawChunk {
+        let mut body = Vec::with_capacity(
+            HEADER_SIZE + self.loops.len() * LOOP_SIZE + self.sampler_data.len(),
+        );
+
+        for field in [
+            self.manufacturer,
+            self.product,
+            self.sample_period,
+            self.unity_note,
+            self.pitch_fraction,
+            self.smpte_format,
+            self.smpte_offset,
+            self.loops.len() as u32,
+            self.sampler_data.len() as u32,
+        ] {
+            body.extend_from_slice(&field.to_le_bytes());
+        }
+        for sample_loop in &self.loops {
+            for field in [
+                sample_loop.id,
+                sample_loop.kind.to_u32(),
+                sample_loop.start,
+                sample_loop.end,
+                sample_loop.fraction,
+                sample_loop.play_count,
+            ] {
+                body.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        body.extend_from_slice(&self.sampler_data);
+
+        RawChunk::new(FourCC::new(*SMPL_TYPE), body)
+    }
+}
+
+pub(crate) fn sample_period(sample_rate: u32) -> u32 {
+    (1_000_000_000 / sample_rate.max(1) as u64) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut sampler = Sampler::new(44_100);
+        sampler.loops.push(Loop::new(LoopKind::Forward, 100, 199));
+        sampler.loops.push(Loop {
+            id: 3,
+            play_count: 2,
+            ..Loop::new(LoopKind::PingPong, 300, 400)
+        });
+        sampler.sampler_data = vec![1, 2, 3];
+
+        let chunk = sampler.to_chunk();
+        assert_eq!(chunk.data().len(), 36 + 2 * 24 + 3);
+        // 22675 ns per frame at 44.1 kHz
+        assert_eq!(chunk.data()[8..12], 22_675u32.to_le_bytes());
+        assert_eq!(chunk.data()[64..68], 1u32.to_le_bytes());
+
+        assert_eq!(Sampler::from_chunk(&chunk).unwrap(), sampler);
+    }
+
+    #[test]
+    fn keeps_unknown_loop_kinds() {
+        let mut sampler = Sampler::new(48_000);
+        sampler.loops.push(Loop::new(LoopKind::Other(40), 0, 10));
+
+        let decoded = Sampler::from_chunk(&sampler.to_chunk()).unwrap();
+        assert_eq!(decoded.loops[0].kind, LoopKind::Other(40));
+        assert!(matches!(
+            Sampler::from_chunk(&RawChunk::new(FourCC::new(*SMPL_TYPE), vec![0; 20])),
+            Err(Error::TruncatedChunk(_))
+        ));
+    }
+
+    #[test]
+    fn cuts_loops() {
+        let sample_loop = Loop::new(LoopKind::Reverse, 100, 199);
+
+        assert_eq!(sample_loop.cut(&(200..300)), Some(sample_loop));
+        assert_eq!(sample_loop.cut(&(0..50)).map(|cut| cut.start), Some(50));
+        assert_eq!(sample_loop.cut(&(150..160)), None);
+        assert_eq!(Loop::new(LoopKind::Forward, 200, 50).cut(&(10..100)), None);
+    }
+}
@@ -1,156 +1,800 @@
 use crate::{
-    codable::{Decodable, Encodable},
-    header_data::HeaderData,
-    header_format::{Encoding, HeaderFormat},
-    header_riff::HeaderRiff,
+    aiff, au,
+    bext::Bext,
+    channel_mask::ChannelMask,
+    chunk::{Chunk, RawChunk},
+    chunk_reader::{chunks_size, ChunkReader},
+    codable::{read_exact, Decodable, Encodable},
+    codec::{decode_body, encode_body, resolve_encoding, stored_sample_size},
+    container::Container,
+    convert::{convert_samples, Dither},
+    cue::{self, Marker},
+    error::Error,
+    four_cc::FourCC,
+    header_data::{HeaderData, DATA_TYPE},
+    header_ds64::{HeaderDs64, DS64_TYPE},
+    header_fact::{HeaderFact, FACT_TYPE},
+    header_format::{Encoding, HeaderFormat, FMT_TYPE},
+    header_riff::{HeaderRiff, RiffForm},
+    ixml::{self, Ixml, IXML_TYPE, XMP_TYPE},
+    lint::{self, Diagnostic},
+    list_info::ListInfo,
+    raw,
+    repair::{self, Fix},
+    rifx,
+    sample::{ByteOrder, Sample, SampleFormat},
+    sampler::{sample_period, Loop, Sampler},
+    wave64,
 };
-use std::io::Read;
-use std::{fs::File, io::Write, mem, slice};
+use std::io::{Cursor, Read};
+use std::{fs::File, io::Write, ops::Range, slice::ChunksExact};
 
 const HEADER_SIZE: usize = 36;
-const SAMPLE_MAX: usize = 32_767;
 
 // #[derive(Debug)]
-#[repr(C)]
-pub struct Wav<T: Copy = i16> {
+pub struct Wav<T: Sample = i16> {
+    container: Container,
     riff: HeaderRiff,
+    // Only for RF64 and BW64
+    ds64: Option<HeaderDs64>,
     format: HeaderFormat,
+    fact: Option<HeaderFact>,
     data: HeaderData,
+    // Chunks we don't interpret, written back in order before data
+    chunks: Vec<RawChunk>,
     body: Vec<T>,
 }
 
-impl<T: Copy> Wav<T> {
+impl<T: Sample> Wav<T> {
+    // f32 and f64 bodies pick IEEE encoding on their own
     pub fn new(encoding: Encoding, channels: usize, sample_rate: usize) -> Self {
-        let sample_size = mem::size_of::<T>(); // method not yet accessible
+        let encoding = resolve_encoding(encoding, T::FORMAT);
+        let sample_size = stored_sample_size(encoding, T::FORMAT);
         let fmt = HeaderFormat::new(encoding, channels, sample_rate, sample_size);
 
-        Self {
+        let mut wav = Self {
+            container: Container::Wave,
             riff: HeaderRiff::new(),
+            ds64: None,
             format: fmt,
+            fact: None,
             data: HeaderData::new(),
+            chunks: Vec::new(),
             body: Vec::new(),
-        }
+        };
+        wav.update_sizes();
+
+        wav
     }
 
-    pub fn set_channels(&mut self, channels: u16) -> &Self {
-        self.format.channels(channels);
-        self
+    // Fails when a frame of that many channels doesn't fit the block_align field
+    pub fn set_channels(&mut self, channels: u16) -> Result<&Self, Error> {
+        self.format.channels(channels)?;
+        self.update_sizes();
+        Ok(self)
     }
-    pub fn set_encoding(&mut self, encoding: Encoding) -> &Self {
-        self.format.encoding(encoding);
-        self
+    // A-law and mu-law are companded from and to the linear i16 body
+    pub fn set_encoding(&mut self, encoding: Encoding) -> Result<&Self, Error> {
+        let encoding = resolve_encoding(encoding, T::FORMAT);
+        let mut format = self.format.clone();
+        format.encoding(encoding);
+        format.sample_size(stored_sample_size(encoding, T::FORMAT))?;
+        self.format = format;
+        self.update_sizes();
+        Ok(self)
     }
     pub fn set_sample_rate(&mut self, sample_rate: u32) -> &Self {
         self.format.sample_rate(sample_rate);
+        if let Some(mut sampler) = self.sampler() {
+            sampler.sample_period = sample_period(sample_rate);
+            self.set_sampler(sampler);
+        }
+        self
+    }
+    // Writes a WAVE_FORMAT_EXTENSIBLE fmt chunk assigning channels to speakers
+    pub fn set_channel_mask(&mut self, channel_mask: ChannelMask) -> &Self {
+        self.format.extensible(channel_mask);
+        self.update_sizes();
         self
     }
 
+    // Layout used by encode, decoding picks it from the file
+    pub fn set_container(&mut self, container: Container) -> &Self {
+        self.container = container;
+        self
+    }
+    pub fn container(&self) -> Container {
+        self.container
+    }
+
+    // RF64 and BW64 lift the 4 GiB limit, RIFF is promoted to RF64 on its own when it gets too big
+    pub fn set_form(&mut self, form: RiffForm) -> &Self {
+        self.riff.set_form(form);
+        self.update_sizes();
+        self
+    }
+    pub fn form(&self) -> RiffForm {
+        self.riff.get_form()
+    }
+
+    pub fn format(&self) -> &HeaderFormat {
+        &self.format
+    }
+    pub fn channel_mask(&self) -> Option<ChannelMask> {
+        self.format.get_channel_mask()
+    }
+
     // Appends audio data to the end of the wav & updates the header
     pub fn push_body(&mut self, chunk: Vec<T>) -> &Self {
         self.body.extend(chunk);
+        self.update_sizes();
+
+        self
+    }
+
+    // Appends a chunk to be written as is before the data chunk
+    pub fn push_chunk(&mut self, chunk: RawChunk) -> &Self {
+        self.chunks.push(chunk);
+        self.update_sizes();
+
+        self
+    }
+    pub fn chunks(&self) -> &[RawChunk] {
+        &self.chunks
+    }
+
+    // Broadcast Wave metadata, if there's a bext chunk that can be read
+    pub fn bext(&self) -> Option<Bext> {
+        self.chunks
+            .iter()
+            .find(|chunk| Bext::is_bext(chunk))
+            .and_then(|chunk| Bext::from_chunk(chunk).ok())
+    }
+    // Replaces the bext chunk where it stands, a new one goes first as BWF readers expect
+    pub fn set_bext(&mut self, bext: Bext) -> &Self {
+        match self.chunks.iter().position(Bext::is_bext) {
+            Some(position) => self.chunks[position] = bext.to_chunk(),
+            None => self.chunks.insert(0, bext.to_chunk()),
+        }
+        self.update_sizes();
+        self
+    }
+    // Position of the first sample on the timeline, in samples since midnight, through bext.
+    // A bext chunk that can't be read is an error rather than replaced.
+    pub fn set_time_reference(&mut self, time_reference: u64) -> Result<&Self, Error> {
+        let mut bext = self
+            .parse_chunk(Bext::is_bext, Bext::from_chunk)?
+            .unwrap_or_default();
+        bext.time_reference(time_reference);
+        Ok(self.set_bext(bext))
+    }
+    pub fn time_reference(&self) -> Option<u64> {
+        self.bext().map(|bext| bext.get_time_reference())
+    }
+
+    // Text tags of the LIST/INFO chunk, if there's one that can be read
+    pub fn info(&self) -> Option<ListInfo> {
+        self.chunks
+            .iter()
+            .find(|chunk| ListInfo::is_info(chunk))
+            .and_then(|chunk| ListInfo::from_chunk(chunk).ok())
+    }
+    // Replaces the LIST/INFO chunk where it stands, an empty one removes it
+    pub fn set_info(&mut self, info: ListInfo) -> &Self {
+        let chunk = (!info.is_empty()).then(|| info.to_chunk());
+        self.replace_chunk(ListInfo::is_info, chunk);
+        self
+    }
+    // Sets a single INFO tag, other tags are kept. A LIST/INFO chunk that can't be read
+    // is an error rather than replaced, so its tags aren't lost.
+    pub fn set_info_text(&mut self, id: FourCC, text: &str) -> Result<&Self, Error> {
+        let mut info = self
+            .parse_chunk(ListInfo::is_info, ListInfo::from_chunk)?
+            .unwrap_or_default();
+        info.set(id, text);
+        Ok(self.set_info(info))
+    }
+    pub fn remove_info_text(&mut self, id: FourCC) -> Result<&Self, Error> {
+        let Some(mut info) = self.parse_chunk(ListInfo::is_info, ListInfo::from_chunk)? else {
+            return Ok(self);
+        };
+        info.remove(id);
+        Ok(self.set_info(info))
+    }
+    pub fn set_title(&mut self, title: &str) -> Result<&Self, Error> {
+        self.set_info_text(ListInfo::TITLE, title)
+    }
+    pub fn set_artist(&mut self, artist: &str) -> Result<&Self, Error> {
+        self.set_info_text(ListInfo::ARTIST, artist)
+    }
+    pub fn set_software(&mut self, software: &str) -> Result<&Self, Error> {
+        self.set_info_text(ListInfo::SOFTWARE, software)
+    }
+    pub fn set_comment(&mut self, comment: &str) -> Result<&Self, Error> {
+        self.set_info_text(ListInfo::COMMENT, comment)
+    }
+    pub fn set_creation_date(&mut self, date: &str) -> Result<&Self, Error> {
+        self.set_info_text(ListInfo::CREATION_DATE, date)
+    }
 
-        // Update fields dependent on body size
+    // iXML from field recorders, its XML is left as found
+    pub fn ixml(&self) -> Option<Ixml> {
+        self.chunks
+            .iter()
+            .find(|chunk| ixml::is_ixml(chunk))
+            .map(|chunk| Ixml::new(&ixml::chunk_text(chunk)))
+    }
+    // An empty document removes the chunk
+    pub fn set_ixml(&mut self, xml: &str) -> &Self {
+        let chunk = (!xml.is_empty()).then(|| RawChunk::new(FourCC::new(*IXML_TYPE), xml.into()));
+        self.replace_chunk(ixml::is_ixml, chunk);
+        self
+    }
+    // XMP packet of the _PMX chunk
+    pub fn xmp(&self) -> Option<String> {
+        self.chunks
+            .iter()
+            .find(|chunk| ixml::is_xmp(chunk))
+            .map(ixml::chunk_text)
+    }
+    pub fn set_xmp(&mut self, xml: &str) -> &Self {
+        let chunk = (!xml.is_empty()).then(|| RawChunk::new(FourCC::new(*XMP_TYPE), xml.into()));
+        self.replace_chunk(ixml::is_xmp, chunk);
+        self
+    }
+
+    // Cue points with their labels, notes and region lengths, empty when there's no cue chunk
+    pub fn markers(&self) -> Vec<Marker> {
+        let adtl = self.chunks.iter().find(|chunk| cue::is_adtl(chunk));
+        self.chunks
+            .iter()
+            .find(|chunk| cue::is_cue(chunk))
+            .and_then(|chunk| cue::decode_markers(chunk, adtl).ok())
+            .unwrap_or_default()
+    }
+    // Rewrites the cue and LIST/adtl chunks together, no markers removes both.
+    // LIST/adtl entries other than the markers' own are kept. A cue or LIST/adtl chunk
+    // that can't be read is an error rather than replaced.
+    pub fn set_markers(&mut self, markers: Vec<Marker>) -> Result<&Self, Error> {
+        let current = self.chunks.iter().find(|chunk| cue::is_adtl(chunk));
+        let replaced = self
+            .parse_chunk(cue::is_cue, |chunk| cue::decode_markers(chunk, None))?
+            .unwrap_or_default();
+        let (cue, adtl) = cue::encode_markers(&markers, &replaced, current)?;
+        let cue = (!markers.is_empty()).then_some(cue);
+        self.replace_chunk(cue::is_cue, cue);
+        self.replace_chunk(cue::is_adtl, adtl);
+        Ok(self)
+    }
+
+    // Root note and loops for samplers
+    pub fn sampler(&self) -> Option<Sampler> {
+        self.chunks
+            .iter()
+            .find(|chunk| Sampler::is_smpl(chunk))
+            .and_then(|chunk| Sampler::from_chunk(chunk).ok())
+    }
+    pub fn set_sampler(&mut self, sampler: Sampler) -> &Self {
+        self.replace_chunk(Sampler::is_smpl, Some(sampler.to_chunk()));
+        self
+    }
+    pub fn loops(&self) -> Vec<Loop> {
+        self.sampler()
+            .map(|sampler| sampler.loops)
+            .unwrap_or_default()
+    }
+    // Adds a smpl chunk if there's none yet, one that can't be read is an error
+    pub fn set_loops(&mut self, loops: Vec<Loop>) -> Result<&Self, Error> {
+        let mut sampler = self
+            .parse_chunk(Sampler::is_smpl, Sampler::from_chunk)?
+            .unwrap_or_else(|| Sampler::new(self.format.get_sample_rate()));
+        sampler.loops = loops;
+        Ok(self.set_sampler(sampler))
+    }
+
+    // The first chunk `is` picks, parsed. None when there's no such chunk, an error when
+    // there is one that doesn't parse.
+    fn parse_chunk<C>(
+        &self,
+        is: fn(&RawChunk) -> bool,
+        parse: fn(&RawChunk) -> Result<C, Error>,
+    ) -> Result<Option<C>, Error> {
+        self.chunks
+            .iter()
+            .find(|chunk| is(chunk))
+            .map(parse)
+            .transpose()
+    }
+
+    // Replaces the first chunk `is` picks where it stands, or appends it; None removes it
+    fn replace_chunk(&mut self, is: fn(&RawChunk) -> bool, chunk: Option<RawChunk>) {
+        let position = self.chunks.iter().position(is);
+        match (position, chunk) {
+            (Some(position), Some(chunk)) => self.chunks[position] = chunk,
+            (Some(position), None) => {
+                self.chunks.remove(position);
+            }
+            (None, Some(chunk)) => self.chunks.push(chunk),
+            (None, None) => {}
+        }
+        self.update_sizes();
+    }
+
+    // Update fields dependent on body and chunks size
+    fn update_sizes(&mut self) {
         let ssize = self.get_sample_size();
         let samples = self.body.len();
 
+        // Non-PCM formats must carry a fact chunk, PCM ones may
+        if self.format.get_encoding() != Encoding::LPCM {
+            self.fact.get_or_insert_with(HeaderFact::new);
+        }
+        if let Some(fact) = &mut self.fact {
+            fact.set_size(samples, self.format.get_channels() as usize);
+        }
+
         // RIFF
         self.riff.set_size(self.get_header_size(), samples, ssize);
+        if self.riff.get_size() > u32::MAX as u64 {
+            self.riff.set_form(RiffForm::Rf64);
+        }
+        // DS64, which in turn takes room in RIFF
+        if self.riff.get_form().is_64_bit() {
+            self.ds64.get_or_insert_with(HeaderDs64::new);
+            self.riff.set_size(self.get_header_size(), samples, ssize);
+        } else {
+            self.ds64 = None;
+        }
         // DATA
         self.data.set_size(samples, ssize);
+        self.data.resize(self.data.get_size(), self.ds64.is_some());
+
+        if let Some(ds64) = &mut self.ds64 {
+            let frames = samples / (self.format.get_channels() as usize).max(1);
+            ds64.set_sizes(self.riff.get_size(), self.data.get_size(), frames as u64);
+        }
+    }
+
+    pub fn body(&self) -> &[T] {
+        &self.body
+    }
+
+    // Cuts frames out of the body, markers and loops after the cut move back with the audio.
+    // Markers inside it go, regions lose what overlaps it and loops it runs through are dropped.
+    pub fn remove_frames(&mut self, frames: Range<usize>) -> &Self {
+        let end = frames.end.min(self.len_frames());
+        let start = frames.start.min(end);
+        let channels = self.format.get_channels() as usize;
+        self.body.drain(start * channels..end * channels);
+
+        // Cue points and loops can't go past frame u32::MAX
+        if let Ok(start) = u32::try_from(start) {
+            let removed = start..u32::try_from(end).unwrap_or(u32::MAX);
+            let markers = self.markers();
+            if !markers.is_empty() {
+                let markers = markers
+                    .iter()
+                    .filter_map(|marker| marker.cut(&removed))
+                    .collect();
+                // Can't fail, markers() found both chunks readable
+                let _ = self.set_markers(markers);
+            }
+            if let Some(mut sampler) = self.sampler() {
+                sampler.loops = sampler
+                    .loops
+                    .iter()
+                    .filter_map(|sample_loop| sample_loop.cut(&removed))
+                    .collect();
+                self.set_sampler(sampler);
+            }
+        }
+        self.update_sizes();
 
         self
     }
+    // Keeps the first `frames` frames
+    pub fn truncate_frames(&mut self, frames: usize) -> &Self {
+        self.remove_frames(frames..usize::MAX)
+    }
+
+    // Builds a wav out of one buffer per channel, all of the same length
+    pub fn from_planar<C: AsRef<[T]>>(
+        encoding: Encoding,
+        sample_rate: usize,
+        channels: &[C],
+    ) -> Result<Self, Error> {
+        let frames = match channels.first() {
+            Some(channel) => channel.as_ref().len(),
+            None => {
+                return Err(Error::InvalidFormat {
+                    field: "channels",
+                    expected: 1,
+                    found: 0,
+                })
+            }
+        };
+        if let Some((channel, samples)) = channels
+            .iter()
+            .map(|samples| samples.as_ref())
+            .enumerate()
+            .find(|(_, samples)| samples.len() != frames)
+        {
+            return Err(Error::ChannelLength {
+                channel,
+                expected: frames as u64,
+                found: samples.len() as u64,
+            });
+        }
+
+        let mut body = Vec::with_capacity(frames * channels.len());
+        for frame in 0..frames {
+            body.extend(channels.iter().map(|samples| samples.as_ref()[frame]));
+        }
+
+        let mut wav = Self::new(encoding, channels.len(), sample_rate);
+        wav.push_body(body);
+
+        Ok(wav)
+    }
+
+    // One buffer per channel, the reverse of from_planar
+    pub fn to_planar(&self) -> Result<Vec<Vec<T>>, Error> {
+        let channels = self.format.get_channels() as usize;
+        let mut planar = vec![Vec::with_capacity(self.len_frames()); channels];
+
+        for frame in self.frames()? {
+            for (channel, sample) in planar.iter_mut().zip(frame) {
+                channel.push(*sample);
+            }
+        }
+
+        Ok(planar)
+    }
+
+    // Interleaved samples, one slice of `channels` samples per frame
+    pub fn frames(&self) -> Result<ChunksExact<'_, T>, Error> {
+        self.check_frames()?;
+
+        Ok(self.body.chunks_exact(self.format.get_channels() as usize))
+    }
+
+    // Samples of a single channel, in order
+    pub fn channel(&self, channel: usize) -> Result<impl Iterator<Item = T> + '_, Error> {
+        let channels = self.format.get_channels();
+        if channel >= channels as usize {
+            return Err(Error::NoSuchChannel { channel, channels });
+        }
+        self.check_frames()?;
+
+        Ok(self
+            .body
+            .iter()
+            .skip(channel)
+            .step_by(channels as usize)
+            .copied())
+    }
+
+    // Number of whole frames in the body
+    pub fn len_frames(&self) -> usize {
+        self.body.len() / (self.format.get_channels() as usize).max(1)
+    }
+
+    fn check_frames(&self) -> Result<(), Error> {
+        let channels = self.format.get_channels();
+        if channels == 0 || !self.body.len().is_multiple_of(channels as usize) {
+            return Err(Error::PartialFrame {
+                samples: self.body.len() as u64,
+                channels,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Same audio with another sample type, companding is kept when U still fits it.
+    // Fails when wider samples make a frame too big for the block_align field.
+    pub fn convert<U: Sample>(&self, dither: Dither) -> Result<Wav<U>, Error> {
+        let encoding = match self.format.get_encoding() {
+            Encoding::ALAW | Encoding::MULAW if U::FORMAT == SampleFormat::I16 => {
+                self.format.get_encoding()
+            }
+            _ => resolve_encoding(Encoding::LPCM, U::FORMAT),
+        };
+
+        let mut format = self.format.clone();
+        format.encoding(encoding);
+        format.sample_size(stored_sample_size(encoding, U::FORMAT))?;
+
+        let mut wav = Wav {
+            container: self.container,
+            riff: HeaderRiff::new(),
+            ds64: None,
+            format,
+            fact: None,
+            data: HeaderData::new(),
+            chunks: self.chunks.clone(),
+            body: convert_samples(&self.body, dither),
+        };
+        wav.riff.set_form(self.form());
+        wav.update_sizes();
+
+        Ok(wav)
+    }
+
+    // Everything that goes before the samples: RIFF, fmt, other chunks and the data header
+    pub(crate) fn encode_header(&self) -> Result<Vec<u8>, Error> {
+        let mut vec = Vec::new();
+
+        vec.extend_from_slice(&self.riff.encode()?);
+        if let Some(ds64) = &self.ds64 {
+            vec.extend_from_slice(&ds64.encode()?);
+        }
+        vec.extend_from_slice(&self.format.encode()?);
+        if let Some(fact) = &self.fact {
+            vec.extend_from_slice(&fact.encode()?);
+        }
+        for chunk in &self.chunks {
+            vec.extend_from_slice(&chunk.encode()?);
+        }
+        vec.extend_from_slice(&self.data.encode()?);
+
+        Ok(vec)
+    }
+
+    // Builds a wav out of chunks read from another container, sizes are recomputed
+    pub(crate) fn from_parts(
+        container: Container,
+        format: HeaderFormat,
+        fact: Option<HeaderFact>,
+        chunks: Vec<RawChunk>,
+        body: &[u8],
+    ) -> Result<Self, Error> {
+        let mut wav = Wav {
+            container,
+            riff: HeaderRiff::new(),
+            ds64: None,
+            format,
+            fact,
+            data: HeaderData::new(),
+            chunks,
+            body: Vec::new(),
+        };
+        wav.set_body_bytes(body)?;
+        wav.update_sizes();
+
+        Ok(wav)
+    }
+
+    // Decodes the body as stored on disk, T has to be able to hold the file's sample format
+    fn set_body_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.format.check_sample_format(T::FORMAT)?;
+
+        let sample_size = self.get_sample_size();
+        if !bytes.len().is_multiple_of(sample_size) {
+            return Err(Error::SizeMismatch {
+                id: FourCC::new(*DATA_TYPE),
+                expected: (bytes.len() - bytes.len() % sample_size) as u64,
+                found: bytes.len() as u64,
+            });
+        }
+
+        self.body = decode_body(&self.format, bytes)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_fact(&self) -> Option<&HeaderFact> {
+        self.fact.as_ref()
+    }
+
+    // Samples as stored on disk, without pad
+    pub(crate) fn encode_body(&self) -> Result<Vec<u8>, Error> {
+        let mut vec = Vec::new();
+        encode_body(&self.format, &self.body, &mut vec)?;
+
+        Ok(vec)
+    }
+
+    pub(crate) fn encode_riff(&self) -> Result<Vec<u8>, Error> {
+        let body_size = self.body.len() * self.get_sample_size();
+
+        let mut vec = self.encode_header()?;
+
+        encode_body(&self.format, &self.body, &mut vec)?;
+        if body_size % 2 == 1 {
+            vec.push(0);
+        }
+
+        Ok(vec)
+    }
+
+    pub(crate) fn get_riff_size(&self) -> u64 {
+        self.riff.get_size()
+    }
+
+    pub(crate) fn get_ds64(&self) -> Option<&HeaderDs64> {
+        self.ds64.as_ref()
+    }
+
+    // Where the fact sample length sits, relative to the start of the file
+    pub(crate) fn get_fact_offset(&self) -> Option<u64> {
+        self.fact
+            .map(|_| 12 + self.get_ds64_size() as u64 + 8 + self.format.get_le_size() as u64 + 8)
+    }
 
     fn get_sample_size(&self) -> usize {
-        mem::size_of::<T>()
+        self.format.get_sample_size()
     }
     fn get_header_size(&self) -> usize {
-        // No need to use mem::size_of() here, since we know the size of the struct
+        // HEADER_SIZE holds a basic 16 bytes fmt chunk, extensions come on top
+        let format_extension = self.format.get_le_size() as usize - 16;
+        let fact_size = match self.fact {
+            Some(fact) => 8 + fact.get_le_size() as usize,
+            None => 0,
+        };
         HEADER_SIZE
+            + self.get_ds64_size()
+            + format_extension
+            + fact_size
+            + chunks_size(&self.chunks) as usize
+    }
+    fn get_ds64_size(&self) -> usize {
+        match &self.ds64 {
+            Some(ds64) => 8 + ds64.get_le_size() as usize,
+            None => 0,
+        }
     }
 
     // read_new reads a wav file to a new instance of structure
-    pub fn read_new(path: &str) -> Result<Self, &str> {
-        let maybe_file = File::open(path);
-        if maybe_file.is_err() {
-            return Err("File not found");
-        }
+    pub fn read_new(path: &str) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
 
-        let mut file = maybe_file.unwrap();
+        Self::decode_new(&mut file)
+    }
 
-        let wav = Self::decode_new(&mut file);
+    // Decodes a RIFF/WAVE file whose sizes are zero or stale, working them out from its
+    // length. What had to be fixed is returned with the wav, repair_file fixes the file itself.
+    pub fn decode_lenient<R: Read>(mut reader: R) -> Result<(Self, Vec<Fix>), Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let plan = repair::plan(&mut Cursor::new(&bytes))?;
+        plan.apply(&mut bytes);
 
-        Ok(wav)
+        Ok((Self::decode_new(&bytes[..])?, plan.fixes))
+    }
+
+    // Headerless samples laid out as `format` says, e.g. a telephony capture
+    pub fn decode_raw<R: Read>(
+        reader: R,
+        format: HeaderFormat,
+        byte_order: ByteOrder,
+    ) -> Result<Self, Error> {
+        raw::decode(reader, format, byte_order)
+    }
+    // Just the samples, no header
+    pub fn encode_raw(&self, byte_order: ByteOrder) -> Result<Vec<u8>, Error> {
+        raw::encode(self, byte_order)
+    }
+
+    // Lints the file as it would be written to RIFF, e.g. markers left past the end
+    pub fn validate(&self) -> Result<Vec<Diagnostic>, Error> {
+        Ok(lint::lint_bytes(&self.encode_riff()?))
     }
 
-    pub fn write_to_file(&mut self, path: &str) -> Result<(), &str> {
-        let mut file = File::create(path).unwrap();
+    pub fn write_to_file(&mut self, path: &str) -> Result<(), Error> {
+        let mut file = File::create(path)?;
 
-        let buffer_vec = self.encode();
+        let buffer_vec = self.encode()?;
         let buffer = buffer_vec.as_slice();
 
-        file.write_all(buffer).unwrap();
+        file.write_all(buffer)?;
 
         Ok(())
     }
 }
 
-impl<T: Copy> Encodable for Wav<T> {
-    fn encode(&self) -> Vec<u8> {
-        let mut vec = Vec::new();
-
-        vec.extend_from_slice(&self.riff.encode());
-        vec.extend_from_slice(&self.format.encode());
-        vec.extend_from_slice(&self.data.encode());
+impl<T: Sample> Encodable for Wav<T> {
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        match self.container {
+            Container::Wave => self.encode_riff(),
+            Container::Rifx => rifx::encode(self),
+            Container::Wave64 => wave64::encode(self),
+            Container::Aiff | Container::AiffC(_) => aiff::encode(self),
+            Container::Au => au::encode(self),
+        }
+    }
+}
 
-        let body = unsafe {
-            slice::from_raw_parts(
-                self.body.as_ptr() as *const u8,
-                self.body.len() * self.get_sample_size(),
-            )
-        };
-        vec.extend_from_slice(body);
+impl<T: Sample> Decodable for Wav<T> {
+    // Picks the container from the first bytes of the file
+    fn decode_new<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0; 4];
+        read_exact(&mut reader, &mut magic, b"RIFF")?;
+        let reader = Cursor::new(magic).chain(reader);
 
-        vec
+        match &magic {
+            wave64::RIFF_MAGIC => wave64::decode(reader),
+            aiff::FORM_ID => aiff::decode(reader),
+            rifx::RIFX_ID => rifx::decode(reader),
+            au::AU_MAGIC => au::decode(reader),
+            _ => Self::decode_riff(reader),
+        }
     }
 }
 
-impl<T: Copy> Decodable for Wav<T> {
-    fn decode_new<R: Read>(mut reader: R) -> Self {
+impl<T: Sample> Wav<T> {
+    pub(crate) fn decode_riff<R: Read>(reader: R) -> Result<Self, Error> {
+        let (riff, ds64, mut walker) = ChunkReader::open(reader)?;
+
+        let mut format = None;
+        let mut fact = None;
+        let mut data = None;
+        let mut chunks = Vec::new();
+        let mut body_buffer: Vec<u8> = Vec::new();
+        let mut data_size = 0;
+
+        while let Some(header) = walker.next_header()? {
+            let encoded_header = header.encode()?;
+
+            match header.get_be_id() {
+                FMT_TYPE if format.is_none() => {
+                    let body = walker.read_body()?;
+                    let chunk = (&encoded_header[..]).chain(&body[..]);
+                    format = Some(HeaderFormat::decode_new(chunk)?);
+                }
+                FACT_TYPE if fact.is_none() => {
+                    let body = walker.read_body()?;
+                    let chunk = (&encoded_header[..]).chain(&body[..]);
+                    fact = Some(HeaderFact::decode_new(chunk)?);
+                }
+                DATA_TYPE if data.is_none() => {
+                    let mut header = HeaderData::decode_new(&encoded_header[..])?;
+                    data_size = walker.body_size();
+                    header.resize(data_size, ds64.is_some());
+                    data = Some(header);
+                    body_buffer = walker.read_partial_body()?;
+                }
+                // Only the first of each counts, the others would be written ahead of data
+                FMT_TYPE | FACT_TYPE | DATA_TYPE | DS64_TYPE => {}
+                _ => chunks.push(RawChunk::new(header.id(), walker.read_body()?)),
+            }
+        }
+
         let mut wav = Wav {
-            riff: HeaderRiff::decode_new(&mut reader),
-            format: HeaderFormat::decode_new(&mut reader),
-            data: HeaderData::decode_new(&mut reader),
+            container: Container::Wave,
+            riff,
+            ds64,
+            format: format.ok_or(Error::MissingChunk(FourCC::new(*FMT_TYPE)))?,
+            fact,
+            data: data.ok_or(Error::MissingChunk(FourCC::new(*DATA_TYPE)))?,
+            chunks,
             body: Vec::<T>::new(),
         };
 
-        // Set sample size for format afterwards to not spoil trait decode interface :(
-        wav.format.sample_size(wav.get_sample_size());
-
-        let mut body_buffer: Vec<u8> = Vec::new();
-        reader.read_to_end(&mut body_buffer).unwrap();
+        wav.set_body_bytes(&body_buffer)?;
 
-        let body = unsafe {
-            slice::from_raw_parts(
-                body_buffer.as_ptr() as *const T,
-                body_buffer.len() / wav.get_sample_size(),
-            )
-        };
-        wav.body = body.to_vec();
+        // Sizes follow what was read. Data cut short by the end of the file keeps the sizes
+        // it declares, so the mismatch shows in validate() and is left to decode_lenient.
+        let truncated = (body_buffer.len() as u64) < data_size;
+        let missing_fact = wav.fact.is_none() && wav.format.get_encoding() != Encoding::LPCM;
+        if !truncated || missing_fact {
+            wav.update_sizes();
+        }
 
-        wav
+        Ok(wav)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{sample::I24, sampler::LoopKind};
 
     #[test]
     fn check_size() {
-        let riff_size = HeaderRiff::default().encode().len();
-        let format_size = HeaderFormat::default().encode().len();
-        let data_size = HeaderData::default().encode().len();
+        let riff_size = HeaderRiff::default().encode().unwrap().len();
+        let format_size = HeaderFormat::default().encode().unwrap().len();
+        let data_size = HeaderData::default().encode().unwrap().len();
 
         println!("riff_size: {}", riff_size);
         println!("format_size: {}", format_size);
@@ -164,7 +808,7 @@ mod test {
         const FILE_PATH: &str = "test_assets/header_only.wav";
 
         let wav = Wav::<i16>::read_new(FILE_PATH).unwrap();
-        let code_buffer = wav.encode();
+        let code_buffer = wav.encode().unwrap();
 
         let mut file = File::open(FILE_PATH).unwrap();
         let mut file_buffer = Vec::new();
@@ -173,6 +817,105 @@ mod test {
         assert_eq!(code_buffer, file_buffer);
     }
 
+    #[test]
+    fn reports_data_past_end_of_file() {
+        let file = std::fs::read("test_assets/header_only.wav").unwrap();
+
+        // The header claims samples the file doesn't hold
+        let wav = Wav::<i16>::decode_new(&file[..]).unwrap();
+        assert!(wav.body().is_empty());
+        let diagnostics = wav.validate().unwrap();
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.chunk == Some(FourCC::new(*DATA_TYPE))));
+
+        let (wav, fixes) = Wav::<i16>::decode_lenient(&file[..]).unwrap();
+        assert!(!fixes.is_empty());
+        assert_eq!(wav.validate().unwrap(), []);
+    }
+
+    #[test]
+    fn widens_short_float_fmt() {
+        // IEEE with a 16-byte fmt, written back with cbSize
+        let mut file = b"RIFF\x34\0\0\0WAVEfmt \x10\0\0\0".to_vec();
+        file.extend_from_slice(&[3, 0, 1, 0, 0x40, 0x1f, 0, 0, 0, 0x7d, 0, 0, 4, 0, 32, 0]);
+        file.extend_from_slice(b"fact\x04\0\0\0\x01\0\0\0data\x04\0\0\0");
+        file.extend_from_slice(&0.5f32.to_le_bytes());
+        assert_eq!(file.len(), 60);
+
+        let encoded = Wav::<f32>::decode_new(&file[..]).unwrap().encode().unwrap();
+        assert_eq!(encoded.len(), 62);
+        assert_eq!(encoded[4..8], 54u32.to_le_bytes());
+        assert_eq!(crate::lint::lint(&encoded[..]).unwrap(), []);
+    }
+
+    #[test]
+    fn recomputes_sizes_on_round_trip() {
+        // 18-byte fmt with cbSize, RIFF size off by 2
+        let mut file = b"RIFF\x2a\0\0\0WAVEfmt \x12\0\0\0".to_vec();
+        file.extend_from_slice(&[1, 0, 1, 0, 0x40, 0x1f, 0, 0, 0x80, 0x3e, 0, 0, 2, 0, 16, 0]);
+        file.extend_from_slice(&[0, 0]);
+        file.extend_from_slice(b"data\x04\0\0\0\x01\0\x02\0");
+        assert_eq!(file.len(), 50);
+
+        let encoded = Wav::<i16>::decode_new(&file[..]).unwrap().encode().unwrap();
+        assert_eq!(encoded.len(), 48);
+        assert_eq!(u32::from_le_bytes(encoded[4..8].try_into().unwrap()), 40);
+        assert_eq!(encoded[40..44], 4u32.to_le_bytes());
+
+        // data says 100 bytes but holds 4, past the end of RIFF. Only lenient decoding fixes it.
+        let mut file = encoded.clone();
+        file[40..44].copy_from_slice(&100u32.to_le_bytes());
+        assert!(matches!(
+            Wav::<i16>::decode_new(&file[..]),
+            Err(Error::TruncatedChunk(_))
+        ));
+        let (wav, _) = Wav::<i16>::decode_lenient(&file[..]).unwrap();
+        assert_eq!(wav.body(), [1, 2]);
+        assert_eq!(wav.encode().unwrap(), encoded);
+
+        // A second fmt and data after the first ones
+        let mut file = encoded.clone();
+        file.extend_from_slice(&encoded[12..]);
+        let riff_size = file.len() as u32 - 8;
+        file[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        let wav = Wav::<i16>::decode_new(&file[..]).unwrap();
+        assert!(wav.chunks().is_empty());
+        assert_eq!(wav.encode().unwrap(), encoded);
+    }
+
+    // Written by hand, so a host that gets byte order or sample layout wrong can't match them
+    fn check_golden<T: Sample>(name: &str, samples: Vec<T>) {
+        let golden = std::fs::read(format!("test_assets/golden_{}.wav", name)).unwrap();
+
+        let mut wav = Wav::<T>::new(Encoding::LPCM, 1, 8_000);
+        wav.push_body(samples);
+        assert_eq!(wav.encode().unwrap(), golden, "{}", name);
+
+        let decoded = Wav::<T>::decode_new(&golden[..]).unwrap();
+        assert_eq!(decoded.body(), wav.body(), "{}", name);
+    }
+
+    #[test]
+    fn matches_golden_files() {
+        check_golden::<u8>("u8", vec![0, 1, 127, 128, 255]);
+        check_golden::<i16>("i16", vec![0, 1, -1, 0x1234, i16::MIN, i16::MAX]);
+        check_golden(
+            "i24",
+            vec![
+                I24::new(0),
+                I24::new(1),
+                I24::new(-1),
+                I24::new(0x123456),
+                I24::MIN,
+                I24::MAX,
+            ],
+        );
+        check_golden::<i32>("i32", vec![0, 1, -1, 0x12345678, i32::MIN, i32::MAX]);
+        check_golden::<f32>("f32", vec![0.0, 0.5, -0.5, 1.0, -1.0, 0.1]);
+        check_golden::<f64>("f64", vec![0.0, 0.5, -0.5, 1.0, -1.0, 0.1]);
+    }
+
     #[test]
     fn rewrite_compare() {
         const FILE_PATH: &str = "test_assets/sine.wav";
@@ -182,6 +925,461 @@ mod test {
         wav.write_to_file(FILE_PATH_OUT).expect("Write err")
     }
 
+    #[test]
+    fn read_missing_file() {
+        let wav = Wav::<i16>::read_new("test_assets/does_not_exist.wav");
+
+        assert!(matches!(wav, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn decode_truncated_header() {
+        let encoded = [82, 73, 70, 70, 36, 0, 0, 0, 87, 65, 86, 69, 102, 109, 116];
+        let wav = Wav::<i16>::decode_new(&encoded[..]);
+
+        assert!(matches!(wav, Err(Error::TruncatedChunk(_))));
+    }
+
+    #[test]
+    fn refuses_mismatched_sample_type() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.push_body(vec![1, 2]);
+        let encoded = wav.encode().unwrap();
+
+        let wav = Wav::<i16>::decode_new(&encoded[..]);
+        assert!(matches!(
+            wav,
+            Err(Error::IncompatibleSample {
+                file: SampleFormat::U8,
+                sample: SampleFormat::I16
+            })
+        ));
+    }
+
+    #[test]
+    fn round_trips_packed_24_bit() {
+        let mut wav = Wav::<I24>::new(Encoding::LPCM, 2, 48_000);
+        wav.push_body(vec![I24::MIN, I24::new(-2), I24::new(3), I24::MAX]);
+
+        let encoded = wav.encode().unwrap();
+        // 12 bytes of samples, 24 bits per sample and 6 bytes per frame
+        assert_eq!(encoded.len(), 44 + 12);
+        assert_eq!(encoded[32..36], [6, 0, 24, 0]);
+
+        let decoded = Wav::<I24>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.body(), wav.body());
+    }
+
+    #[test]
+    fn round_trips_channel_mask() {
+        let mut wav = Wav::<I24>::new(Encoding::LPCM, 6, 48_000);
+        wav.set_channel_mask(ChannelMask::SURROUND_5_1);
+        wav.push_body(vec![I24::new(1); 12]);
+
+        let encoded = wav.encode().unwrap();
+        let decoded = Wav::<I24>::decode_new(&encoded[..]).unwrap();
+
+        assert_eq!(decoded.channel_mask(), Some(ChannelMask::SURROUND_5_1));
+        assert_eq!(decoded.body(), wav.body());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn round_trips_companded() {
+        for encoding in [Encoding::ALAW, Encoding::MULAW] {
+            let mut wav = Wav::<i16>::new(encoding, 2, 8_000);
+            wav.push_body(vec![0, -8, 1_000, -1_000, i16::MAX, i16::MIN]);
+
+            let encoded = wav.encode().unwrap();
+            // 18 bytes fmt, 12 bytes fact and one byte per sample
+            assert_eq!(encoded.len(), 12 + 26 + 12 + 8 + 6);
+            assert_eq!(encoded[38..50], [102, 97, 99, 116, 4, 0, 0, 0, 3, 0, 0, 0]);
+
+            let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+            assert_eq!(decoded.format().get_encoding(), encoding);
+            assert_eq!(decoded.encode().unwrap(), encoded);
+
+            // Companding is lossy, but close enough
+            for (a, b) in decoded.body().iter().zip(wav.body()) {
+                assert!((*a as i32 - *b as i32).abs() <= 1_024);
+            }
+        }
+    }
+
+    #[test]
+    fn selects_ieee_for_floats() {
+        let mut wav = Wav::<f32>::new(Encoding::LPCM, 1, 48_000);
+        wav.push_body(vec![0.0, 0.5, -1.0]);
+
+        assert_eq!(wav.format().get_encoding(), Encoding::IEEE);
+        assert_eq!(wav.format().get_bits_per_sample(), 32);
+
+        let encoded = wav.encode().unwrap();
+        // Float formats carry cbSize and a fact chunk holding the frame count
+        assert_eq!(encoded[16..22], [18, 0, 0, 0, 3, 0]);
+        assert_eq!(encoded[38..50], [102, 97, 99, 116, 4, 0, 0, 0, 3, 0, 0, 0]);
+
+        let decoded = Wav::<f32>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.body(), wav.body());
+
+        let wav = Wav::<f64>::new(Encoding::IEEE, 2, 48_000);
+        assert_eq!(wav.format().get_bits_per_sample(), 64);
+        assert_eq!(wav.format().get_block_align(), 16);
+    }
+
+    #[test]
+    fn rejects_mismatched_float_combinations() {
+        let mut wav = Wav::<u8>::new(Encoding::IEEE, 1, 8_000);
+        wav.push_body(vec![1]);
+        assert!(matches!(
+            wav.encode(),
+            Err(Error::UnsupportedSampleFormat {
+                encoding: Encoding::IEEE,
+                bits_per_sample: 8
+            })
+        ));
+
+        let mut wav = Wav::<f32>::new(Encoding::LPCM, 1, 8_000);
+        wav.push_body(vec![0.25]);
+        let encoded = wav.encode().unwrap();
+        assert!(matches!(
+            Wav::<i32>::decode_new(&encoded[..]),
+            Err(Error::IncompatibleSample {
+                file: SampleFormat::F32,
+                sample: SampleFormat::I32
+            })
+        ));
+    }
+
+    #[test]
+    fn converts_between_sample_types() {
+        let mut wav = Wav::<f32>::new(Encoding::LPCM, 2, 48_000);
+        wav.set_channel_mask(ChannelMask::STEREO);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"note"), vec![1, 2]));
+        wav.push_body(vec![0.0, -1.0, 0.5, 1.0]);
+
+        let converted = wav.convert::<u8>(Dither::None).unwrap();
+        assert_eq!(converted.format().get_encoding(), Encoding::LPCM);
+        assert_eq!(converted.format().get_bits_per_sample(), 8);
+        assert_eq!(converted.format().get_block_align(), 2);
+        assert_eq!(converted.channel_mask(), Some(ChannelMask::STEREO));
+        assert_eq!(converted.body(), [128, 0, 192, 255]);
+
+        let decoded = Wav::<u8>::decode_new(&converted.encode().unwrap()[..]).unwrap();
+        assert_eq!(decoded.body(), converted.body());
+        assert_eq!(decoded.chunks(), wav.chunks());
+
+        let mut wav = Wav::<i16>::new(Encoding::ALAW, 1, 8_000);
+        wav.push_body(vec![0, 1_000]);
+        assert_eq!(
+            wav.convert::<i16>(Dither::None)
+                .unwrap()
+                .format()
+                .get_encoding(),
+            Encoding::ALAW
+        );
+        assert_eq!(
+            wav.convert::<f64>(Dither::None)
+                .unwrap()
+                .format()
+                .get_encoding(),
+            Encoding::IEEE
+        );
+
+        // 65535 channels of u8 fit a frame, 8 bytes each don't
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_channels(u16::MAX).unwrap();
+        assert!(wav.convert::<f64>(Dither::None).is_err());
+        assert!(Wav::<f64>::new(Encoding::IEEE, 1, 8_000)
+            .set_channels(u16::MAX)
+            .is_err());
+    }
+
+    #[test]
+    fn frame_and_channel_views() {
+        let left = [1i16, 2, 3];
+        let right = [-1i16, -2, -3];
+        let wav = Wav::from_planar(Encoding::LPCM, 8_000, &[left, right]).unwrap();
+
+        assert_eq!(wav.body(), [1, -1, 2, -2, 3, -3]);
+        assert_eq!(wav.len_frames(), 3);
+        assert_eq!(wav.frames().unwrap().nth(1).unwrap(), [2, -2]);
+        assert_eq!(wav.channel(1).unwrap().collect::<Vec<_>>(), right);
+        assert_eq!(wav.to_planar().unwrap(), [left, right]);
+
+        assert!(matches!(
+            wav.channel(2),
+            Err(Error::NoSuchChannel {
+                channel: 2,
+                channels: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn checks_frame_lengths() {
+        let wav = Wav::<i16>::from_planar(Encoding::LPCM, 8_000, &[vec![1, 2], vec![3]]);
+        assert!(matches!(
+            wav,
+            Err(Error::ChannelLength {
+                channel: 1,
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.push_body(vec![1, 2, 3]);
+        assert!(matches!(
+            wav.frames(),
+            Err(Error::PartialFrame {
+                samples: 3,
+                channels: 2
+            })
+        ));
+        assert!(wav.to_planar().is_err());
+    }
+
+    #[test]
+    fn round_trips_rf64() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_form(RiffForm::Rf64);
+        wav.push_body(vec![1, 2, 3]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(
+            encoded[..16],
+            [82, 70, 54, 52, 255, 255, 255, 255, 87, 65, 86, 69, 100, 115, 54, 52]
+        );
+        // data size sits in ds64 only
+        assert_eq!(encoded[76..80], [255; 4]);
+        assert_eq!(encoded.len(), 80 + 6);
+
+        let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.form(), RiffForm::Rf64);
+        assert_eq!(decoded.body(), wav.body());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+
+        wav.set_form(RiffForm::Riff);
+        assert_eq!(wav.encode().unwrap().len(), 44 + 6);
+    }
+
+    #[test]
+    fn keeps_unknown_chunks() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"JUNK"), vec![0; 5]));
+        wav.push_body(vec![1, 2, 3]);
+
+        let encoded = wav.encode().unwrap();
+        let decoded = Wav::<u8>::decode_new(&encoded[..]).unwrap();
+
+        assert_eq!(decoded.chunks(), wav.chunks());
+        assert_eq!(decoded.body, [1, 2, 3]);
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn edits_info_tags() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"JUNK"), vec![0; 2]));
+        wav.set_title("Tone").unwrap();
+        wav.set_software("little_wav").unwrap();
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"note"), vec![1]));
+        wav.set_title("Sine").unwrap();
+        wav.push_body(vec![1, 2, 3]);
+
+        let decoded = Wav::<u8>::decode_new(&wav.encode().unwrap()[..]).unwrap();
+        // Edited where it stands
+        assert_eq!(decoded.chunks()[1].id(), FourCC::new(*b"LIST"));
+        let info = decoded.info().unwrap();
+        assert_eq!(info.get(ListInfo::TITLE), Some("Sine"));
+        assert_eq!(info.get(ListInfo::SOFTWARE), Some("little_wav"));
+
+        wav.remove_info_text(ListInfo::TITLE).unwrap();
+        assert_eq!(wav.info().unwrap().entries().len(), 1);
+        wav.set_info(ListInfo::new());
+        assert!(wav.info().is_none());
+        assert_eq!(wav.chunks().len(), 2);
+
+        // Tags that can't be read are kept rather than replaced
+        let broken = RawChunk::new(FourCC::new(*b"LIST"), b"INFOIART\x09\0\0\0Me".to_vec());
+        wav.push_chunk(broken.clone());
+        assert!(wav.set_comment("take 2").is_err());
+        assert_eq!(wav.chunks()[2], broken);
+    }
+
+    #[test]
+    fn stamps_time_reference() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 48_000);
+        wav.set_title("Take 3").unwrap();
+        assert_eq!(wav.time_reference(), None);
+
+        wav.set_time_reference(123_456_789).unwrap();
+        wav.push_body(vec![1, -1]);
+
+        let decoded = Wav::<i16>::decode_new(&wav.encode().unwrap()[..]).unwrap();
+        assert_eq!(decoded.chunks()[0].id(), FourCC::new(*b"bext"));
+        assert_eq!(decoded.time_reference(), Some(123_456_789));
+        assert_eq!(decoded.bext().unwrap().get_version(), 2);
+
+        let mut bext = decoded.bext().unwrap();
+        bext.description("Take 3");
+        wav.set_bext(bext);
+        assert_eq!(wav.chunks().len(), 2);
+        assert_eq!(wav.time_reference(), Some(123_456_789));
+    }
+
+    #[test]
+    fn keeps_unreadable_bext() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 48_000);
+        let bext = RawChunk::new(FourCC::new(*b"bext"), b"Too short".to_vec());
+        wav.push_chunk(bext.clone());
+
+        assert!(wav.set_time_reference(1).is_err());
+        assert_eq!(wav.chunks(), [bext]);
+    }
+
+    #[test]
+    fn keeps_markers_and_loops_with_the_audio() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.push_body(vec![0; 2 * 1_000]);
+        let mut intro = Marker::region(1, 0, 300);
+        intro.label = Some("Intro".to_string());
+        wav.set_markers(vec![intro, Marker::new(2, 400), Marker::new(3, 800)])
+            .unwrap();
+        wav.set_loops(vec![
+            Loop::new(LoopKind::Forward, 100, 199),
+            Loop::new(LoopKind::PingPong, 700, 899),
+        ])
+        .unwrap();
+
+        let mut decoded = Wav::<i16>::decode_new(&wav.encode().unwrap()[..]).unwrap();
+        assert_eq!(decoded.markers(), wav.markers());
+        assert_eq!(decoded.loops(), wav.loops());
+        assert_eq!(decoded.sampler().unwrap().sample_period, 125_000);
+
+        decoded.remove_frames(250..450);
+        assert_eq!(decoded.len_frames(), 800);
+        let markers = decoded.markers();
+        assert_eq!(markers.len(), 2);
+        assert_eq!((markers[0].position, markers[0].length), (0, 250));
+        assert_eq!(markers[0].label.as_deref(), Some("Intro"));
+        assert_eq!(markers[1].position, 600);
+        assert_eq!(
+            decoded.loops(),
+            [
+                Loop::new(LoopKind::Forward, 100, 199),
+                Loop::new(LoopKind::PingPong, 500, 699),
+            ]
+        );
+
+        decoded.truncate_frames(650);
+        assert_eq!(decoded.loops().len(), 1);
+        decoded.set_markers(Vec::new()).unwrap();
+        assert!(decoded
+            .chunks()
+            .iter()
+            .all(|chunk| chunk.id() != FourCC::new(*b"cue ")));
+        assert_eq!(decoded.chunks().len(), 1);
+    }
+
+    #[test]
+    fn keeps_unreadable_smpl_and_cue() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 8_000);
+        let smpl = RawChunk::new(FourCC::new(*b"smpl"), vec![1, 2, 3]);
+        let cue = RawChunk::new(FourCC::new(*b"cue "), vec![1, 0, 0, 0]);
+        wav.push_chunk(smpl.clone());
+        wav.push_chunk(cue.clone());
+
+        assert!(wav.set_loops(Vec::new()).is_err());
+        assert!(wav.set_markers(vec![Marker::new(1, 0)]).is_err());
+        assert_eq!(wav.chunks(), [smpl, cue]);
+    }
+
+    #[test]
+    fn keeps_foreign_adtl_entries() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 1, 8_000);
+        let mut intro = Marker::new(1, 0);
+        intro.label = Some("Intro".to_string());
+        wav.set_markers(vec![intro]).unwrap();
+        let (id, mut adtl) = wav.chunks()[1].clone().into_parts();
+        adtl.extend_from_slice(b"file\x06\0\0\0\x01\0\0\0ab");
+        wav.replace_chunk(cue::is_adtl, Some(RawChunk::new(id, adtl)));
+
+        wav.set_markers(vec![Marker::new(2, 10)]).unwrap();
+        let (_, adtl) = wav.chunks()[1].clone().into_parts();
+        assert_eq!(adtl, b"adtlfile\x06\0\0\0\x01\0\0\0ab");
+        assert_eq!(wav.markers(), [Marker::new(2, 10)]);
+    }
+
+    #[test]
+    fn keeps_xml_chunks_untouched() {
+        let ixml = "<BWFXML><SCENE>4</SCENE><TAKE>2</TAKE></BWFXML>";
+        let mut recorded = Wav::<i16>::new(Encoding::LPCM, 1, 48_000);
+        // Padded the way some recorders do
+        recorded.push_chunk(RawChunk::new(
+            FourCC::new(*b"iXML"),
+            format!("{}\0\0\0", ixml).into(),
+        ));
+        recorded.set_xmp("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>");
+        recorded.push_body(vec![1, 2]);
+        let encoded = recorded.encode().unwrap();
+
+        let mut wav = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(wav.encode().unwrap(), encoded);
+        assert_eq!(wav.ixml().unwrap().as_str(), ixml);
+        assert_eq!(wav.ixml().unwrap().take().as_deref(), Some("2"));
+        assert_eq!(
+            wav.xmp().as_deref(),
+            Some("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>")
+        );
+
+        wav.set_ixml("<BWFXML><SCENE>5</SCENE></BWFXML>");
+        assert_eq!(wav.chunks()[0].id(), FourCC::new(*b"iXML"));
+        assert_eq!(wav.ixml().unwrap().scene().as_deref(), Some("5"));
+        wav.set_xmp("");
+        assert!(wav.xmp().is_none());
+    }
+
+    #[test]
+    fn finds_reordered_chunks() {
+        let mut format = Vec::new();
+        format.extend(
+            HeaderFormat::new(Encoding::LPCM, 1, 8_000, 2)
+                .encode()
+                .unwrap(),
+        );
+        let mut data = HeaderData::new();
+        data.set_size(2, 2);
+
+        // LIST first, data before fmt and a trailing odd-sized chunk
+        let mut body = b"WAVE".to_vec();
+        body.extend(
+            RawChunk::new(FourCC::new(*b"LIST"), b"INFO".to_vec())
+                .encode()
+                .unwrap(),
+        );
+        body.extend(data.encode().unwrap());
+        body.extend([1, 0, 2, 0]);
+        body.extend(format);
+        body.extend(
+            RawChunk::new(FourCC::new(*b"bext"), vec![9])
+                .encode()
+                .unwrap(),
+        );
+
+        let mut file = b"RIFF".to_vec();
+        file.extend((body.len() as u32).to_le_bytes());
+        file.extend(body);
+
+        let wav = Wav::<i16>::decode_new(&file[..]).unwrap();
+
+        assert_eq!(wav.body, [1, 2]);
+        assert_eq!(wav.chunks().len(), 2);
+        assert_eq!(wav.chunks()[0].id(), FourCC::new(*b"LIST"));
+        assert_eq!(wav.chunks()[1].data(), [9]);
+    }
+
     #[test]
     fn do_sine() {
         const FILE_PATH_OUT: &str = "test_assets/_sine_out_2.wav";
@@ -198,8 +1396,9 @@ mod test {
         let mut samples = Vec::<i16>::new();
         for i in 0..num_samples {
             let t = i as f64 / sr as f64;
-            let sample = (t * 440.0 * 2.0 * std::f64::consts::PI).sin() * SAMPLE_MAX as f64;
-            samples.push(sample as i16);
+            samples.push(i16::from_f64(
+                (t * 440.0 * 2.0 * std::f64::consts::PI).sin(),
+            ));
         }
 
         wav.push_body(samples);
@@ -222,7 +1421,7 @@ mod test {
         let mut samples = Vec::<u8>::new();
         for i in 0..num_samples {
             // let sample = i * 5 & i >> 7 | i * 3 & i >> 10;
-            let sample = i * 1 & i >> 7 | i * 3 & i >> 10;
+            let sample = (i & (i >> 7)) | ((i * 3) & (i >> 10));
             samples.push(sample as u8);
         }
 
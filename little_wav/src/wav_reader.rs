@@ -0,0 +1,221 @@
+use crate::{
+    chunk::{Chunk, RawChunk},
+    chunk_reader::ChunkReader,
+    codable::{Decodable, Encodable},
+    codec::decode_body,
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+    header_format::{HeaderFormat, FMT_TYPE},
+    sample::Sample,
+};
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    marker::PhantomData,
+};
+
+// Reads a WAV file frame by frame, only the headers are kept in memory
+pub struct WavReader<R, T: Sample = i16> {
+    reader: R,
+    format: HeaderFormat,
+    chunks: Vec<RawChunk>,
+
+    // Where the samples start in the stream and how many bytes of them there are
+    data_offset: u64,
+    data_size: u64,
+    // Current frame
+    position: u64,
+
+    sample: PhantomData<T>,
+}
+
+impl<R: Read + Seek, T: Sample> WavReader<R, T> {
+    // Parses every header up front, leaving the samples for later
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut format = None;
+        let mut data = None;
+        let mut chunks = Vec::new();
+
+        let (_, _, mut walker) = ChunkReader::open(&mut reader)?;
+        while let Some(header) = walker.next_header()? {
+            match header.get_be_id() {
+                FMT_TYPE if format.is_none() => {
+                    let body = walker.read_body()?;
+                    let encoded_header = header.encode()?;
+                    let chunk = (&encoded_header[..]).chain(&body[..]);
+                    format = Some(HeaderFormat::decode_new(chunk)?);
+                }
+                DATA_TYPE if data.is_none() => {
+                    let offset = walker.get_mut().stream_position()?;
+                    data = Some((offset, walker.body_size()));
+                    walker.seek_body()?;
+                }
+                _ => chunks.push(RawChunk::new(header.id(), walker.read_body()?)),
+            }
+        }
+
+        let format = format.ok_or(Error::MissingChunk(FourCC::new(*FMT_TYPE)))?;
+        let (data_offset, data_size) = data.ok_or(Error::MissingChunk(FourCC::new(*DATA_TYPE)))?;
+
+        // Cut short to what the stream holds, as read_partial does
+        let stream_size = reader.seek(SeekFrom::End(0))?;
+        let data_size = data_size.min(stream_size.saturating_sub(data_offset));
+
+        format.check_sample_format(T::FORMAT)?;
+
+        let mut wav_reader = Self {
+            reader,
+            format,
+            chunks,
+            data_offset,
+            data_size,
+            position: 0,
+            sample: PhantomData,
+        };
+        wav_reader.seek(0)?;
+
+        Ok(wav_reader)
+    }
+
+    pub fn format(&self) -> &HeaderFormat {
+        &self.format
+    }
+    // Chunks other than fmt and data, in file order
+    pub fn chunks(&self) -> &[RawChunk] {
+        &self.chunks
+    }
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Total number of whole frames in the data chunk
+    pub fn len_frames(&self) -> u64 {
+        self.data_size / self.get_frame_size()
+    }
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // Moves to the given frame, the next read starts from there
+    pub fn seek(&mut self, frame: u64) -> Result<(), Error> {
+        if frame > self.len_frames() {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek past the last frame",
+            )));
+        }
+
+        let offset = self.data_offset + frame * self.get_frame_size();
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.position = frame;
+
+        Ok(())
+    }
+
+    // Reads the next frame, one sample per channel, or None at the end of data
+    pub fn read_frame(&mut self) -> Result<Option<Vec<T>>, Error> {
+        let frame = self.read_block(1)?;
+
+        Ok(if frame.is_empty() { None } else { Some(frame) })
+    }
+
+    // Reads up to `frames` interleaved frames, fewer at the end of data
+    pub fn read_block(&mut self, frames: usize) -> Result<Vec<T>, Error> {
+        let frames = (frames as u64).min(self.len_frames() - self.position);
+
+        let mut buffer = vec![0; (frames * self.get_frame_size()) as usize];
+        self.reader
+            .read_exact(&mut buffer)
+            .map_err(|err| Error::from_read(err, DATA_TYPE))?;
+        self.position += frames;
+
+        decode_body(&self.format, &buffer)
+    }
+
+    // Iterates over the rest of data in blocks of `frames` frames
+    pub fn blocks(&mut self, frames: usize) -> Blocks<'_, R, T> {
+        Blocks {
+            reader: self,
+            frames,
+        }
+    }
+
+    fn get_frame_size(&self) -> u64 {
+        self.format.get_block_align() as u64
+    }
+}
+
+pub struct Blocks<'a, R, T: Sample> {
+    reader: &'a mut WavReader<R, T>,
+    frames: usize,
+}
+
+impl<R: Read + Seek, T: Sample> Iterator for Blocks<'_, R, T> {
+    type Item = Result<Vec<T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.read_block(self.frames) {
+            Ok(block) if block.is_empty() => None,
+            block => Some(block),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{header_format::Encoding, header_riff::RiffForm, wav::Wav};
+    use std::io::Cursor;
+
+    fn stereo_ramp(frames: i16) -> Vec<u8> {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"JUNK"), vec![0; 3]));
+        wav.push_body((0..frames * 2).collect());
+
+        wav.encode().unwrap()
+    }
+
+    #[test]
+    fn reads_blocks() {
+        let mut reader = WavReader::<_, i16>::new(Cursor::new(stereo_ramp(5))).unwrap();
+
+        assert_eq!(reader.len_frames(), 5);
+        assert_eq!(reader.chunks().len(), 1);
+
+        let blocks = reader.blocks(2).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(blocks, [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+        assert_eq!(reader.position(), 5);
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn seeks_to_frame() {
+        let mut reader = WavReader::<_, i16>::new(Cursor::new(stereo_ramp(5))).unwrap();
+
+        reader.seek(3).unwrap();
+        assert_eq!(reader.read_frame().unwrap(), Some(vec![6, 7]));
+        assert!(reader.seek(6).is_err());
+    }
+
+    #[test]
+    fn reads_rf64() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.set_form(RiffForm::Bw64);
+        wav.push_body((0..10).collect());
+
+        let mut reader = WavReader::<_, i16>::new(Cursor::new(wav.encode().unwrap())).unwrap();
+        assert_eq!(reader.len_frames(), 5);
+        assert_eq!(reader.read_block(5).unwrap(), wav.body());
+    }
+
+    #[test]
+    fn reads_truncated_data() {
+        let mut file = stereo_ramp(5);
+        file.truncate(file.len() - 6);
+
+        let mut reader = WavReader::<_, i16>::new(Cursor::new(file)).unwrap();
+
+        assert_eq!(reader.len_frames(), 3);
+        assert_eq!(reader.read_block(10).unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    chunk::{decode_chunk, padded, Chunk, ChunkHeader, RawChunk},
+    codable::Decodable,
+    codec::decode_sample,
+    container::Container,
+    error::Error,
+    four_cc::FourCC,
+    header_data::DATA_TYPE,
+    header_ds64::{HeaderDs64, DS64_TYPE},
+    header_fact::{HeaderFact, FACT_TYPE},
+    header_format::{Encoding, HeaderFormat, FMT_TYPE},
+    header_riff::{HeaderRiff, RiffForm},
+    sample::Sample,
+    wav::Wav,
+};
+use std::{iter::FusedIterator, marker::PhantomData, slice::ChunksExact};
+
+// A RIFF/WAVE file borrowed from a byte slice, e.g. a memory-mapped file. Headers are parsed
+// up front, samples are decoded one at a time as they're read so nothing gets copied.
+pub struct WavRef<'a, T: Sample = i16> {
+    form: RiffForm,
+    format: HeaderFormat,
+    ds64: Option<HeaderDs64>,
+    // Chunks after the form type
+    body: &'a [u8],
+    data: &'a [u8],
+    sample: PhantomData<T>,
+}
+
+impl<'a, T: Sample> WavRef<'a, T> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        let riff = HeaderRiff::decode_new(bytes)?;
+        let mut body = riff_body(bytes, riff.get_le_size() as u64);
+
+        // RF64 and BW64 sizes are in the ds64 chunk that has to come first
+        let mut ds64 = None;
+        if riff.get_form().is_64_bit() {
+            let (id, chunk) = ChunkSlices::new(body, None)
+                .next()
+                .filter(|(id, _)| id.as_array() == DS64_TYPE)
+                .ok_or(Error::MissingChunk(FourCC::new(*DS64_TYPE)))?;
+            let parsed: HeaderDs64 = decode_chunk(id, chunk)?;
+            body = riff_body(bytes, parsed.get_riff_size());
+            ds64 = Some(parsed);
+        }
+
+        let mut format = None;
+        let mut data = None;
+        for (id, chunk) in ChunkSlices::new(body, ds64.as_ref()) {
+            match id.as_array() {
+                FMT_TYPE if format.is_none() => format = Some(decode_chunk(id, chunk)?),
+                DATA_TYPE if data.is_none() => data = Some(chunk),
+                _ => {}
+            }
+        }
+
+        let format: HeaderFormat = format.ok_or(Error::MissingChunk(FourCC::new(*FMT_TYPE)))?;
+        let data = data.ok_or(Error::MissingChunk(FourCC::new(*DATA_TYPE)))?;
+        format.check_sample_format(T::FORMAT)?;
+
+        // Whole samples only, a recording cut short may end mid-sample
+        let data = &data[..data.len() - data.len() % format.get_sample_size()];
+
+        Ok(Self {
+            form: riff.get_form(),
+            format,
+            ds64,
+            body,
+            data,
+            sample: PhantomData,
+        })
+    }
+
+    pub fn format(&self) -> &HeaderFormat {
+        &self.format
+    }
+    // Samples as stored on disk
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+    // Chunks other than ds64, fmt, fact and data, in file order
+    pub fn chunks(&self) -> impl Iterator<Item = (FourCC, &'a [u8])> + '_ {
+        let mut data_seen = false;
+        ChunkSlices::new(self.body, self.ds64.as_ref()).filter(move |(id, _)| {
+            match id.as_array() {
+                DS64_TYPE | FMT_TYPE | FACT_TYPE => false,
+                // Only the first data chunk holds the samples
+                DATA_TYPE if !data_seen => {
+                    data_seen = true;
+                    false
+                }
+                _ => true,
+            }
+        })
+    }
+
+    pub fn len_samples(&self) -> usize {
+        self.data.len() / self.format.get_sample_size()
+    }
+    // Number of whole frames in the data chunk
+    pub fn len_frames(&self) -> usize {
+        self.len_samples() / (self.format.get_channels() as usize).max(1)
+    }
+
+    // Interleaved samples, decoded from the slice on the fly
+    pub fn samples(&self) -> Samples<'a, T> {
+        Samples {
+            bytes: self.data.chunks_exact(self.format.get_sample_size()),
+            encoding: self.format.get_encoding(),
+            sample: PhantomData,
+        }
+    }
+    pub fn sample(&self, index: usize) -> Option<T> {
+        let size = self.format.get_sample_size();
+        let start = index.checked_mul(size)?;
+        let bytes = self.data.get(start..start.checked_add(size)?)?;
+
+        Some(decode_sample(self.format.get_encoding(), bytes))
+    }
+
+    // Copies everything into an owned Wav
+    pub fn to_wav(&self) -> Result<Wav<T>, Error> {
+        let fact: Option<HeaderFact> = ChunkSlices::new(self.body, self.ds64.as_ref())
+            .find(|(id, _)| id.as_array() == FACT_TYPE)
+            .map(|(id, chunk)| decode_chunk(id, chunk))
+            .transpose()?;
+        let chunks = self
+            .chunks()
+            .map(|(id, chunk)| RawChunk::new(id, chunk.to_vec()))
+            .collect();
+
+        let mut wav = Wav::from_parts(
+            Container::Wave,
+            self.format.clone(),
+            fact,
+            chunks,
+            self.data,
+        )?;
+        wav.set_form(self.form);
+
+        Ok(wav)
+    }
+}
+
+// The RIFF body after the form type. The slice may hold more than the file, or less
+// when it was cut short.
+fn riff_body(bytes: &[u8], riff_size: u64) -> &[u8] {
+    let end = 8u64.saturating_add(riff_size).min(bytes.len() as u64) as usize;
+    &bytes[12.min(end)..end]
+}
+
+// Chunks of a RIFF body as (id, body) slices, the last one cut short if the slice ends early
+struct ChunkSlices<'a, 'b> {
+    rest: &'a [u8],
+    ds64: Option<&'b HeaderDs64>,
+}
+
+impl<'a, 'b> ChunkSlices<'a, 'b> {
+    fn new(body: &'a [u8], ds64: Option<&'b HeaderDs64>) -> Self {
+        Self { rest: body, ds64 }
+    }
+}
+
+impl<'a> Iterator for ChunkSlices<'a, '_> {
+    type Item = (FourCC, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let header = ChunkHeader::decode_new(self.rest.get(..8)?).ok()?;
+        let id = header.id();
+        let size = match (self.ds64, header.get_le_size()) {
+            (Some(ds64), u32::MAX) => ds64.get_chunk_size(id).unwrap_or(u32::MAX as u64),
+            (_, size) => size as u64,
+        };
+
+        // Sizes from a ds64 table may be anything up to u64::MAX
+        let rest = &self.rest[8..];
+        let end = size.min(rest.len() as u64) as usize;
+        let next = padded(size).min(rest.len() as u64) as usize;
+        self.rest = &rest[next..];
+
+        Some((id, &rest[..end]))
+    }
+}
+
+// Samples of a WavRef, decoded from their little-endian bytes whatever their alignment
+pub struct Samples<'a, T: Sample> {
+    bytes: ChunksExact<'a, u8>,
+    encoding: Encoding,
+    sample: PhantomData<T>,
+}
+
+impl<T: Sample> Iterator for Samples<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let bytes = self.bytes.next()?;
+        Some(decode_sample(self.encoding, bytes))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.bytes.size_hint()
+    }
+}
+
+impl<T: Sample> DoubleEndedIterator for Samples<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        let bytes = self.bytes.next_back()?;
+        Some(decode_sample(self.encoding, bytes))
+    }
+}
+
+impl<T: Sample> ExactSizeIterator for Samples<'_, T> {}
+impl<T: Sample> FusedIterator for Samples<'_, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{codable::Encodable, header_format::Encoding};
+
+    fn tagged_ramp() -> Wav<i16> {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"note"), vec![1, 2, 3]));
+        wav.push_body((-3..3).collect());
+        wav
+    }
+
+    #[test]
+    fn reads_misaligned_slice() {
+        let wav = tagged_ramp();
+        // One byte in, so i16 samples sit on odd addresses
+        let mut bytes = vec![0];
+        bytes.extend(wav.encode().unwrap());
+
+        let view = WavRef::<i16>::new(&bytes[1..]).unwrap();
+        assert_eq!(view.format(), wav.format());
+        assert_eq!(view.len_frames(), 3);
+        assert_eq!(view.samples().collect::<Vec<_>>(), wav.body());
+        assert_eq!(view.samples().next_back(), Some(2));
+        assert_eq!(view.sample(1), Some(-2));
+        assert_eq!(view.sample(6), None);
+        assert_eq!(view.sample(usize::MAX), None);
+        assert_eq!(
+            view.chunks().collect::<Vec<_>>(),
+            [(FourCC::new(*b"note"), &[1u8, 2, 3][..])]
+        );
+        assert_eq!(view.to_wav().unwrap().encode().unwrap(), &bytes[1..]);
+    }
+
+    #[test]
+    fn reads_companded_rf64() {
+        let mut wav = Wav::<i16>::new(Encoding::MULAW, 1, 8_000);
+        wav.set_form(RiffForm::Rf64);
+        wav.push_body(vec![1_000, -1_000, 0]);
+        let bytes = wav.encode().unwrap();
+
+        let view = WavRef::<i16>::new(&bytes).unwrap();
+        assert_eq!(view.data().len(), 3);
+        // Companding is lossy, compare with what the owned decoder makes of it
+        let decoded = Wav::<i16>::decode_new(&bytes[..]).unwrap();
+        assert_eq!(view.samples().collect::<Vec<_>>(), decoded.body());
+        assert_eq!(view.to_wav().unwrap().encode().unwrap(), bytes);
+    }
+
+    #[test]
+    fn reads_truncated_data() {
+        let mut bytes = tagged_ramp().encode().unwrap();
+        bytes.truncate(bytes.len() - 3);
+
+        let view = WavRef::<i16>::new(&bytes).unwrap();
+        assert_eq!(view.len_samples(), 4);
+        assert_eq!(view.len_frames(), 2);
+        assert!(matches!(
+            WavRef::<f32>::new(&bytes),
+            Err(Error::IncompatibleSample { .. })
+        ));
+    }
+
+    #[test]
+    fn survives_oversized_table_entry() {
+        let bytes = crate::header_ds64::test::oversized_junk();
+        assert!(WavRef::<u8>::new(&bytes).is_err());
+    }
+}
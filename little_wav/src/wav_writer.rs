@@ -0,0 +1,357 @@
+use crate::{
+    chunk::RawChunk,
+    codable::Encodable,
+    codec::encode_body,
+    error::Error,
+    four_cc::FourCC,
+    header_ds64::{HeaderDs64, JUNK_TYPE},
+    header_format::{Encoding, HeaderFormat},
+    header_riff::RiffForm,
+    sample::Sample,
+    wav::Wav,
+};
+use std::{
+    io::{self, Seek, SeekFrom, Write},
+    marker::PhantomData,
+};
+
+// Writes a WAV file as samples arrive, sizes are patched in on finalize.
+// Room for a ds64 chunk is kept right after the RIFF header, so that a recording
+// crossing 4 GiB turns into RF64 instead of wrapping its sizes around.
+pub struct WavWriter<W: Write + Seek, T: Sample = i16> {
+    writer: W,
+    format: HeaderFormat,
+    form: RiffForm,
+    // Written over the JUNK placeholder, or the ds64 of the wav we started from
+    ds64: HeaderDs64,
+    // RIFF size past which the file is promoted to RF64
+    limit: u64,
+    // Stream position of the RIFF header
+    start: u64,
+    // Offset of the fact sample length from start, for non-PCM formats
+    fact_offset: Option<u64>,
+    // Bytes from the RIFF header up to the first sample
+    header_size: u64,
+    // Sample bytes written so far
+    data_size: u64,
+    // Sizes on disk match what was written
+    finalized: bool,
+
+    sample: PhantomData<T>,
+}
+
+impl<W: Write + Seek, T: Sample> WavWriter<W, T> {
+    pub fn new(
+        writer: W,
+        encoding: Encoding,
+        channels: usize,
+        sample_rate: usize,
+    ) -> Result<Self, Error> {
+        Self::from_wav(writer, &Wav::new(encoding, channels, sample_rate))
+    }
+
+    // Starts off with the headers, chunks and samples of an existing wav
+    pub fn from_wav(mut writer: W, wav: &Wav<T>) -> Result<Self, Error> {
+        // Nothing gets written for a format no frame fits in
+        wav.format().check()?;
+        let start = writer.stream_position()?;
+
+        let mut header = wav.encode_header()?;
+        let mut fact_offset = wav.get_fact_offset();
+        if !wav.form().is_64_bit() {
+            let junk = RawChunk::new(FourCC::new(*JUNK_TYPE), vec![0; 28]).encode()?;
+            header.splice(12..12, junk);
+            fact_offset = fact_offset.map(|offset| offset + 36);
+        }
+        writer.write_all(&header)?;
+
+        let mut wav_writer = Self {
+            writer,
+            format: wav.format().clone(),
+            form: wav.form(),
+            ds64: wav.get_ds64().cloned().unwrap_or_default(),
+            limit: u32::MAX as u64,
+            start,
+            fact_offset,
+            header_size: header.len() as u64,
+            data_size: 0,
+            finalized: false,
+            sample: PhantomData,
+        };
+
+        // Sizes are valid from the get-go, in case nothing else gets written
+        wav_writer.write_samples(wav.body())?;
+        wav_writer.finalize()?;
+
+        Ok(wav_writer)
+    }
+
+    // Appends interleaved samples right after the ones already written
+    pub fn write_samples(&mut self, samples: &[T]) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        encode_body(&self.format, samples, &mut bytes)?;
+
+        self.writer.write_all(&bytes)?;
+        self.data_size = add(self.data_size, bytes.len() as u64)?;
+        self.finalized = false;
+
+        Ok(())
+    }
+
+    // Patches RIFF and data sizes to match what was written so far.
+    // Can be called any time to checkpoint a long recording, writing may go on afterwards.
+    pub fn finalize(&mut self) -> Result<(), Error> {
+        // Runs from Drop as well, so failures are returned, never panicked on
+        let pad = self.data_size % 2;
+        let data_end = add(self.start, add(self.header_size, self.data_size)?)?;
+        // RIFF size counts everything after its own 8 bytes, pad included
+        let riff_size = add(
+            self.header_size.saturating_sub(8),
+            add(self.data_size, pad)?,
+        )?;
+        let frames = self
+            .data_size
+            .checked_div(self.format.get_block_align() as u64)
+            .ok_or(Error::InvalidFormat {
+                field: "block_align",
+                expected: self.format.get_sample_size() as u64,
+                found: 0,
+            })?;
+
+        // Pad byte gets overwritten by the next samples, if any
+        if pad == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        if riff_size > self.limit {
+            self.form = RiffForm::Rf64;
+        }
+
+        // 32-bit sizes, 0xFFFFFFFF in all of them once the real ones live in ds64
+        let (riff_size_32, data_size_32) = if self.form.is_64_bit() {
+            self.ds64.set_sizes(riff_size, self.data_size, frames);
+            self.writer.seek(SeekFrom::Start(self.start))?;
+            self.writer.write_all(self.form.get_be_id())?;
+            self.writer.seek(SeekFrom::Start(add(self.start, 12)?))?;
+            self.writer.write_all(&self.ds64.encode()?)?;
+
+            (u32::MAX, u32::MAX)
+        } else {
+            (riff_size as u32, self.data_size as u32)
+        };
+
+        self.writer.seek(SeekFrom::Start(add(self.start, 4)?))?;
+        self.writer.write_all(&riff_size_32.to_le_bytes())?;
+
+        self.writer.seek(SeekFrom::Start(add(
+            self.start,
+            self.header_size.saturating_sub(4),
+        )?))?;
+        self.writer.write_all(&data_size_32.to_le_bytes())?;
+
+        if let Some(fact_offset) = self.fact_offset {
+            let frames = u32::try_from(frames).unwrap_or(u32::MAX);
+            self.writer
+                .seek(SeekFrom::Start(add(self.start, fact_offset)?))?;
+            self.writer.write_all(&frames.to_le_bytes())?;
+        }
+
+        self.writer.seek(SeekFrom::Start(data_end))?;
+        self.writer.flush()?;
+        self.finalized = true;
+
+        Ok(())
+    }
+
+    pub fn samples_written(&self) -> u64 {
+        self.data_size / self.format.get_sample_size() as u64
+    }
+    pub fn form(&self) -> RiffForm {
+        self.form
+    }
+
+    // Lets tests promote to RF64 without writing 4 GiB
+    #[cfg(test)]
+    fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+}
+
+impl<W: Write + Seek, T: Sample> Drop for WavWriter<W, T> {
+    fn drop(&mut self) {
+        // Runs while a panic unwinds too, finalize doesn't panic so a crashed recorder
+        // still leaves every sample written
+        if !self.finalized {
+            // Nowhere to report the error to, the file is left as valid as it gets
+            let _ = self.finalize();
+        }
+    }
+}
+
+// Stream offsets past u64 are an I/O error rather than a wrap around
+fn add(a: u64, b: u64) -> Result<u64, Error> {
+    a.checked_add(b).ok_or_else(|| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stream offset overflows u64",
+        ))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codable::{Decodable, Encodable};
+    use std::{io::Cursor, mem, panic};
+
+    // In-memory encoding of the same wav, plus the JUNK chunk the writer reserves
+    fn with_junk(wav: &Wav<i16>) -> Vec<u8> {
+        let mut encoded = wav.encode().unwrap();
+        let junk = RawChunk::new(FourCC::new(*JUNK_TYPE), vec![0; 28]);
+        encoded.splice(12..12, junk.encode().unwrap());
+
+        let riff_size = u32::from_le_bytes([encoded[4], encoded[5], encoded[6], encoded[7]]) + 36;
+        encoded[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        encoded
+    }
+
+    #[test]
+    fn matches_in_memory_encoding() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let mut writer = WavWriter::<_, i16>::new(&mut cursor, Encoding::LPCM, 2, 8_000).unwrap();
+        writer.write_samples(&[1, 2, 3, 4]).unwrap();
+        writer.write_samples(&[5, 6]).unwrap();
+        writer.finalize().unwrap();
+        drop(writer);
+
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 8_000);
+        wav.push_body(vec![1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(cursor.into_inner(), with_junk(&wav));
+    }
+
+    #[test]
+    fn patches_fact_chunk() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let mut writer = WavWriter::<_, i16>::new(&mut cursor, Encoding::MULAW, 1, 8_000).unwrap();
+        writer.write_samples(&[0, 100, -100]).unwrap();
+        writer.write_samples(&[200]).unwrap();
+        assert_eq!(writer.samples_written(), 4);
+        drop(writer);
+
+        let mut wav = Wav::<i16>::new(Encoding::MULAW, 1, 8_000);
+        wav.push_body(vec![0, 100, -100, 200]);
+
+        assert_eq!(cursor.into_inner(), with_junk(&wav));
+    }
+
+    #[test]
+    fn writes_float_with_fact_chunk() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let mut writer = WavWriter::<_, f64>::new(&mut cursor, Encoding::LPCM, 2, 8_000).unwrap();
+        writer.write_samples(&[0.5, -0.5, 1.0, -1.0]).unwrap();
+        drop(writer);
+
+        let wav = Wav::<f64>::decode_new(&cursor.get_ref()[..]).unwrap();
+        assert_eq!(wav.format().get_encoding(), Encoding::IEEE);
+        assert_eq!(wav.body(), [0.5, -0.5, 1.0, -1.0]);
+        assert_eq!(cursor.get_ref()[82..86], 2u32.to_le_bytes());
+
+        // 8-bit IEEE does not exist
+        let result = WavWriter::<_, u8>::new(Cursor::new(Vec::new()), Encoding::IEEE, 1, 8_000);
+        assert!(matches!(result, Err(Error::UnsupportedSampleFormat { .. })));
+    }
+
+    #[test]
+    fn rejects_empty_frames() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let result = WavWriter::<_, i16>::new(&mut cursor, Encoding::LPCM, 0, 8_000);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidFormat {
+                field: "channels",
+                ..
+            })
+        ));
+        drop(result);
+        assert!(cursor.get_ref().is_empty());
+    }
+
+    #[test]
+    fn finalizes_on_drop() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let mut writer = WavWriter::<_, u8>::new(&mut cursor, Encoding::LPCM, 1, 8_000).unwrap();
+        writer.write_samples(&[1, 2, 3]).unwrap();
+        drop(writer);
+
+        let wav = Wav::<u8>::decode_new(&cursor.get_ref()[..]).unwrap();
+        assert_eq!(wav.body(), [1, 2, 3]);
+        // Odd data gets a pad byte
+        assert_eq!(cursor.get_ref().len(), 44 + 36 + 4);
+    }
+
+    #[test]
+    fn finalizes_while_panicking() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let mut writer =
+                WavWriter::<_, u8>::new(&mut cursor, Encoding::LPCM, 1, 8_000).unwrap();
+            writer.write_samples(&[1, 2]).unwrap();
+            writer.finalize().unwrap();
+            writer.write_samples(&[3]).unwrap();
+            panic!("recorder crashed");
+        }));
+        assert!(result.is_err());
+
+        // Samples written after the last checkpoint are kept
+        let wav = Wav::<u8>::decode_new(&cursor.get_ref()[..]).unwrap();
+        assert_eq!(wav.body(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn promotes_to_rf64() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let mut writer = WavWriter::<_, i16>::new(&mut cursor, Encoding::ALAW, 1, 8_000).unwrap();
+        writer.set_limit(150);
+        writer.write_samples(&[1_000; 40]).unwrap();
+        writer.finalize().unwrap();
+        assert_eq!(writer.form(), RiffForm::Riff);
+
+        writer.write_samples(&[-1_000; 40]).unwrap();
+        drop(writer);
+
+        let file = cursor.into_inner();
+        assert_eq!(file[..8], [82, 70, 54, 52, 255, 255, 255, 255]);
+        // ds64 took the place of JUNK, with RIFF size, data size and frame count
+        assert_eq!(file[12..20], [100, 115, 54, 52, 28, 0, 0, 0]);
+        assert_eq!(file[20..28], (file.len() as u64 - 8).to_le_bytes());
+        assert_eq!(file[28..36], 80u64.to_le_bytes());
+        assert_eq!(file[36..44], 80u64.to_le_bytes());
+
+        let wav = Wav::<i16>::decode_new(&file[..]).unwrap();
+        assert_eq!(wav.form(), RiffForm::Rf64);
+        assert_eq!(wav.body().len(), 80);
+        assert!(wav.chunks().is_empty());
+    }
+
+    #[test]
+    fn checkpoint_keeps_file_readable() {
+        let mut cursor = Cursor::new(Vec::new());
+
+        let mut writer = WavWriter::<_, u8>::new(&mut cursor, Encoding::LPCM, 1, 8_000).unwrap();
+        writer.write_samples(&[1, 2, 3]).unwrap();
+        writer.finalize().unwrap();
+        writer.write_samples(&[4]).unwrap();
+        // Simulate a crash: Drop never runs
+        mem::forget(writer);
+
+        let wav = Wav::<u8>::decode_new(&cursor.get_ref()[..]).unwrap();
+        assert_eq!(wav.body(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    chunk::{push_padded, Chunk, ChunkHeader, ChunkId, RawChunk},
+    codable::{read_exact, read_partial, Decodable, Encodable},
+    container::Container,
+    error::Error,
+    four_cc::FourCC,
+    guid::Guid,
+    header_data::DATA_TYPE,
+    header_fact::{HeaderFact, FACT_TYPE},
+    header_format::{HeaderFormat, FMT_TYPE},
+    sample::Sample,
+    wav::Wav,
+};
+use std::io::{self, Read};
+
+// Sony Wave64 is RIFF/WAVE with 16 byte GUIDs in place of FourCCs and 64-bit sizes.
+// Sizes count the 24 byte chunk header too, and chunks are aligned on 8 bytes.
+
+// 66666972-912e-11cf-a5d6-28db04c10000, starts with "riff" on disk
+const RIFF_GUID: Guid = Guid::new([
+    0x72, 0x69, 0x66, 0x66, 0x2e, 0x91, 0xcf, 0x11, 0xa5, 0xd6, 0x28, 0xdb, 0x04, 0xc1, 0x00, 0x00,
+]);
+const WAVE_GUID: Guid = Guid::from_four_cc(*b"wave");
+pub(crate) const RIFF_MAGIC: &[u8; 4] = b"riff";
+
+const HEADER_SIZE: u64 = 24;
+
+// Saturates for sizes near u64::MAX like chunk::padded does
+fn padded_8(size: u64) -> u64 {
+    size.checked_next_multiple_of(8).unwrap_or(u64::MAX)
+}
+
+pub(crate) fn decode<R: Read, T: Sample>(mut reader: R) -> Result<Wav<T>, Error> {
+    let mut guid = [0; 16];
+    let mut buffer_64 = [0; 8];
+
+    read_exact(&mut reader, &mut guid, RIFF_MAGIC)?;
+    let riff = Guid::new(guid);
+    read_exact(&mut reader, &mut buffer_64, RIFF_MAGIC)?;
+    let riff_size = u64::from_le_bytes(buffer_64);
+    read_exact(&mut reader, &mut guid, RIFF_MAGIC)?;
+    let wave = Guid::new(guid);
+
+    for (expected, found) in [(RIFF_GUID, riff), (WAVE_GUID, wave)] {
+        if expected != found {
+            return Err(Error::BadMagic {
+                expected: magic(expected),
+                found: magic(found),
+            });
+        }
+    }
+
+    let mut format = None;
+    let mut fact = None;
+    let mut data = None;
+    let mut chunks = Vec::new();
+
+    let mut remaining = riff_size.saturating_sub(HEADER_SIZE + 16);
+    while remaining >= HEADER_SIZE {
+        // A clean EOF between chunks ends the walk like the end of riff does
+        let mut first = [0; 1];
+        if reader.read(&mut first)? == 0 {
+            break;
+        }
+        guid[0] = first[0];
+        read_exact(&mut reader, &mut guid[1..], RIFF_MAGIC)?;
+        read_exact(&mut reader, &mut buffer_64, RIFF_MAGIC)?;
+
+        let id = ChunkId::from_guid(Guid::new(guid));
+        let four_cc = id.get_four_cc().unwrap_or_default();
+        let size = u64::from_le_bytes(buffer_64).saturating_sub(HEADER_SIZE);
+        let taken = HEADER_SIZE
+            .checked_add(padded_8(size))
+            .ok_or(Error::TruncatedChunk(four_cc))?;
+        remaining = remaining.saturating_sub(taken);
+
+        let body = read_partial(&mut reader, size)?;
+        // Alignment, absent when the chunk is the last thing in a truncated file
+        io::copy(
+            &mut (&mut reader).take(padded_8(size) - size),
+            &mut io::sink(),
+        )?;
+
+        match four_cc.as_array() {
+            DATA_TYPE if data.is_none() => data = Some(body),
+            _ if (body.len() as u64) < size => {
+                return Err(Error::TruncatedChunk(four_cc));
+            }
+            FMT_TYPE if format.is_none() => {
+                format = Some(HeaderFormat::decode_new(riff_chunk(four_cc, &body)?)?);
+            }
+            FACT_TYPE if fact.is_none() => {
+                fact = Some(HeaderFact::decode_new(riff_chunk(four_cc, &body)?)?);
+            }
+            // Only the first of each counts, the others would be written ahead of data
+            FMT_TYPE | FACT_TYPE | DATA_TYPE => {}
+            _ => chunks.push(RawChunk::new(id, body)),
+        }
+    }
+
+    Wav::from_parts(
+        Container::Wave64,
+        format.ok_or(Error::MissingChunk(FourCC::new(*FMT_TYPE)))?,
+        fact,
+        chunks,
+        &data.ok_or(Error::MissingChunk(FourCC::new(*DATA_TYPE)))?,
+    )
+}
+
+pub(crate) fn encode<T: Sample>(wav: &Wav<T>) -> Result<Vec<u8>, Error> {
+    let mut vec = Vec::new();
+
+    vec.extend_from_slice(RIFF_GUID.as_array());
+    // Patched once everything is in
+    vec.extend_from_slice(&0u64.to_le_bytes());
+    vec.extend_from_slice(WAVE_GUID.as_array());
+
+    let format = wav.format();
+    push_guid_chunk(&mut vec, format.get_guid(), &format.encode()?[8..]);
+    if let Some(fact) = wav.get_fact() {
+        push_guid_chunk(&mut vec, fact.get_guid(), &fact.encode()?[8..]);
+    }
+    for chunk in wav.chunks() {
+        push_guid_chunk(&mut vec, chunk.get_guid(), chunk.data());
+    }
+    push_guid_chunk(
+        &mut vec,
+        Guid::from_four_cc(*DATA_TYPE),
+        &wav.encode_body()?,
+    );
+
+    let riff_size = vec.len() as u64;
+    vec[16..24].copy_from_slice(&riff_size.to_le_bytes());
+
+    Ok(vec)
+}
+
+// GUID and a 64-bit size counting the header too
+fn push_guid_chunk(vec: &mut Vec<u8>, guid: Guid, body: &[u8]) {
+    let size = HEADER_SIZE + body.len() as u64;
+
+    vec.extend_from_slice(guid.as_array());
+    push_padded(vec, &size.to_le_bytes(), body, padded_8);
+}
+
+// The same chunk laid out as RIFF, so fmt and fact decoders can be shared
+fn riff_chunk(id: FourCC, body: &[u8]) -> Result<io::Chain<io::Cursor<Vec<u8>>, &[u8]>, Error> {
+    let size = u32::try_from(body.len()).map_err(|_| Error::SizeMismatch {
+        id,
+        expected: u32::MAX as u64,
+        found: body.len() as u64,
+    })?;
+    let header = ChunkHeader::new(id, size).encode()?;
+
+    Ok(io::Cursor::new(header).chain(body))
+}
+
+// Error reports name the GUIDs by their first 4 bytes
+fn magic(guid: Guid) -> FourCC {
+    let bytes = guid.as_array();
+    FourCC::new([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{channel_mask::ChannelMask, header_format::Encoding};
+
+    #[test]
+    fn round_trip() {
+        let mut wav = Wav::<i16>::new(Encoding::LPCM, 2, 44_100);
+        wav.set_container(Container::Wave64);
+        wav.set_channel_mask(ChannelMask::STEREO);
+        wav.push_chunk(RawChunk::new(FourCC::new(*b"note"), vec![1, 2, 3]));
+        wav.push_chunk(RawChunk::new(ChunkId::Guid(Guid::new([9; 16])), vec![4]));
+        wav.push_body(vec![1, -1, 2, -2, 3, -3]);
+
+        let encoded = wav.encode().unwrap();
+        assert_eq!(encoded[..4], *RIFF_MAGIC);
+        assert_eq!(encoded[16..24], (encoded.len() as u64).to_le_bytes());
+        // fmt comes first, 40 byte extensible body padded up to 8
+        assert_eq!(encoded[40..56], *Guid::from_four_cc(*FMT_TYPE).as_array());
+        assert_eq!(encoded[56..64], 64u64.to_le_bytes());
+        assert_eq!(encoded.len() % 8, 0);
+
+        let decoded = Wav::<i16>::decode_new(&encoded[..]).unwrap();
+        assert_eq!(decoded.container(), Container::Wave64);
+        assert_eq!(decoded.format(), wav.format());
+        assert_eq!(decoded.chunks(), wav.chunks());
+        assert_eq!(decoded.body(), wav.body());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn converts_to_riff() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::Wave64);
+        wav.push_body(vec![1, 2, 3]);
+
+        let mut decoded = Wav::<u8>::decode_new(&wav.encode().unwrap()[..]).unwrap();
+        decoded.set_container(Container::Wave);
+
+        let mut riff = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        riff.push_body(vec![1, 2, 3]);
+        assert_eq!(decoded.encode().unwrap(), riff.encode().unwrap());
+    }
+
+    #[test]
+    fn rejects_bad_guid() {
+        let mut encoded = {
+            let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+            wav.set_container(Container::Wave64);
+            wav.encode().unwrap()
+        };
+        encoded[24] = b'W';
+
+        assert!(matches!(
+            Wav::<u8>::decode_new(&encoded[..]),
+            Err(Error::BadMagic { .. })
+        ));
+    }
+
+    #[test]
+    fn drops_duplicates_and_oversized_chunks() {
+        let mut wav = Wav::<u8>::new(Encoding::LPCM, 1, 8_000);
+        wav.set_container(Container::Wave64);
+        wav.push_body(vec![1, 2, 3]);
+        let encoded = wav.encode().unwrap();
+
+        // fmt and data once more after the first ones
+        let mut file = encoded.clone();
+        file.extend_from_slice(&encoded[40..]);
+        let riff_size = file.len() as u64;
+        file[16..24].copy_from_slice(&riff_size.to_le_bytes());
+        let decoded = Wav::<u8>::decode_new(&file[..]).unwrap();
+        assert!(decoded.chunks().is_empty());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+
+        // A chunk claiming all of u64 ahead of fmt
+        let mut file = encoded[..40].to_vec();
+        file.extend_from_slice(Guid::new([9; 16]).as_array());
+        file.extend_from_slice(&u64::MAX.to_le_bytes());
+        file.extend_from_slice(&encoded[40..]);
+        file[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            Wav::<u8>::decode_new(&file[..]),
+            Err(Error::TruncatedChunk(_))
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use little_wav::{Decodable, Dither, Encoding, Error, SampleFormat, Wav, I24};
+use std::fs;
+
+// A Wav of whichever sample type the file holds
+pub enum AnyWav {
+    U8(Wav<u8>),
+    I16(Wav<i16>),
+    I24(Wav<I24>),
+    I32(Wav<i32>),
+    F32(Wav<f32>),
+    F64(Wav<f64>),
+}
+
+// Runs `$body` with `$wav` bound to the Wav inside, whatever its sample type
+macro_rules! with_wav {
+    ($any:expr, $wav:ident => $body:expr) => {
+        match $any {
+            AnyWav::U8($wav) => $body,
+            AnyWav::I16($wav) => $body,
+            AnyWav::I24($wav) => $body,
+            AnyWav::I32($wav) => $body,
+            AnyWav::F32($wav) => $body,
+            AnyWav::F64($wav) => $body,
+        }
+    };
+}
+pub(crate) use with_wav;
+
+impl AnyWav {
+    pub fn read(path: &str) -> Result<Self, Error> {
+        let bytes = fs::read(path)?;
+
+        // Try the common case, the error tells what the file holds otherwise
+        let format = match Wav::<i16>::decode_new(&bytes[..]) {
+            Ok(wav) => return Ok(AnyWav::I16(wav)),
+            Err(Error::IncompatibleSample { file, .. }) => file,
+            Err(err) => return Err(err),
+        };
+
+        Ok(match format {
+            SampleFormat::U8 => AnyWav::U8(Wav::decode_new(&bytes[..])?),
+            SampleFormat::I16 => AnyWav::I16(Wav::decode_new(&bytes[..])?),
+            SampleFormat::I24 => AnyWav::I24(Wav::decode_new(&bytes[..])?),
+            SampleFormat::I32 => AnyWav::I32(Wav::decode_new(&bytes[..])?),
+            SampleFormat::F32 => AnyWav::F32(Wav::decode_new(&bytes[..])?),
+            SampleFormat::F64 => AnyWav::F64(Wav::decode_new(&bytes[..])?),
+        })
+    }
+
+    // Same audio in another sample format, companded to A-law or mu-law when asked
+    pub fn convert(
+        &self,
+        format: SampleFormat,
+        encoding: Encoding,
+        dither: Dither,
+    ) -> Result<Self, Error> {
+        let mut converted = with_wav!(self, wav => match format {
+            SampleFormat::U8 => AnyWav::U8(wav.convert(dither)?),
+            SampleFormat::I16 => AnyWav::I16(wav.convert(dither)?),
+            SampleFormat::I24 => AnyWav::I24(wav.convert(dither)?),
+            SampleFormat::I32 => AnyWav::I32(wav.convert(dither)?),
+            SampleFormat::F32 => AnyWav::F32(wav.convert(dither)?),
+            SampleFormat::F64 => AnyWav::F64(wav.convert(dither)?),
+        });
+        with_wav!(&mut converted, wav => {
+            wav.set_encoding(encoding)?;
+        });
+
+        Ok(converted)
+    }
+}
@@ -0,0 +1,108 @@
+// Command line split into words and `--name value` options
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                // Whatever follows a bare -- is positional, even if it starts with --
+                Some("") => {
+                    parsed.positional.extend(args);
+                    break;
+                }
+                Some(option) => {
+                    let (name, value) = match option.split_once('=') {
+                        Some((name, value)) => (name.to_string(), value.to_string()),
+                        None => {
+                            let value = args
+                                .next()
+                                .ok_or_else(|| format!("--{} needs a value", option))?;
+                            (option.to_string(), value)
+                        }
+                    };
+                    parsed.options.push((name, value));
+                }
+                None => parsed.positional.push(arg),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing {}", name))
+    }
+    // The last one wins when an option is given twice
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Fails on words past the first `positional` and on options not in `options`
+    pub fn check(&self, positional: usize, options: &[&str]) -> Result<(), String> {
+        if let Some(extra) = self.positional.get(positional) {
+            return Err(format!("unexpected argument '{}'", extra));
+        }
+        match self
+            .options
+            .iter()
+            .find(|(option, _)| !options.contains(&option.as_str()))
+        {
+            Some((option, _)) => Err(format!("unknown option --{}", option)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Args, String> {
+        Args::parse(line.split(' ').map(str::to_string))
+    }
+
+    #[test]
+    fn splits_options() {
+        let args = parse("convert --bits 24 in.wav out.wav --bits=16 -- --odd").unwrap();
+
+        assert_eq!(args.positional(1, "input"), Ok("in.wav"));
+        assert_eq!(args.positional(3, "extra"), Ok("--odd"));
+        assert_eq!(args.option("bits"), Some("16"));
+        assert_eq!(args.option("encoding"), None);
+        assert_eq!(args.check(4, &["bits"]), Ok(()));
+        assert_eq!(
+            args.check(3, &["bits"]),
+            Err("unexpected argument '--odd'".to_string())
+        );
+        assert_eq!(
+            args.check(4, &["encoding"]),
+            Err("unknown option --bits".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_missing_values() {
+        assert_eq!(
+            parse("info --bytes").unwrap_err(),
+            "--bytes needs a value".to_string()
+        );
+        assert_eq!(
+            parse("info").unwrap().positional(1, "file"),
+            Err("missing file".to_string())
+        );
+    }
+}
@@ -1,64 +1,475 @@
-use std::env;
-use std::fs;
-use std::io::{Error, Read};
+mod any_wav;
+mod args;
 
-#[derive(Debug, Default)]
-pub struct Data {
-    pub id: i32,
-    pub size: i32,
+use any_wav::{with_wav, AnyWav};
+use args::Args;
+use little_wav::{
+    lint, ByteOrder, Container, Dither, Encoding, FourCC, HeaderFormat, ListInfo, RiffForm,
+    SampleFormat, Severity,
+};
+use std::{env, fs, process::ExitCode};
+
+const USAGE: &str = "usage: wavtool <command> [arguments]
+
+commands:
+  info FILE                  format, duration, chunks and tags
+  chunks FILE [--bytes N]    chunks with their offsets, and the first N bytes of each in hex
+  convert IN OUT [--bits N] [--encoding pcm|float|alaw|mulaw] [--dither none|triangular]
+                 [--container wav|rf64|bw64|rifx|w64|aiff|aifc|au]
+                             the container defaults to what the extension of OUT says
+  validate FILE              problems found in a RIFF/WAVE file, fails on errors
+  meta get FILE [TAG]        INFO tags, or the text of one
+  meta set FILE TAG TEXT [--out FILE]
+                             sets an INFO tag, empty text removes it
+
+tags: title artist album track genre comment copyright date engineer keywords software
+      source subject, or any INFO id such as IMED
+";
+
+// Hex dumped bytes of each chunk when --bytes isn't given
+const DUMP_BYTES: usize = 32;
+
+// Names for the common INFO ids
+const TAGS: [(&str, FourCC); 13] = [
+    ("title", ListInfo::TITLE),
+    ("artist", ListInfo::ARTIST),
+    ("album", ListInfo::ALBUM),
+    ("track", ListInfo::TRACK),
+    ("genre", ListInfo::GENRE),
+    ("comment", ListInfo::COMMENT),
+    ("copyright", ListInfo::COPYRIGHT),
+    ("date", ListInfo::CREATION_DATE),
+    ("engineer", ListInfo::ENGINEER),
+    ("keywords", ListInfo::KEYWORDS),
+    ("software", ListInfo::SOFTWARE),
+    ("source", ListInfo::SOURCE),
+    ("subject", ListInfo::SUBJECT),
+];
+
+fn main() -> ExitCode {
+    let result = Args::parse(env::args().skip(1)).and_then(|args| run(&args));
+
+    match result {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("wavtool: {}", message);
+            ExitCode::FAILURE
+        }
+    }
 }
 
-impl Data {
-    // fn load2(mut reader: impl Read) -> Result<Self, Error> {
-    // let mut data = Data::default();
+fn run(args: &Args) -> Result<ExitCode, String> {
+    let Ok(command) = args.positional(0, "command") else {
+        print!("{}", USAGE);
+        return Ok(ExitCode::FAILURE);
+    };
 
-    // unsafe {
-    //     let buffer: &mut [u8] = std::slice::from_raw_parts_mut(
-    //         data.as_mut_ptr().cast(),
-    //         std::mem::size_of::<Self>(),
-    //     );
+    match command {
+        "info" => info(args),
+        "chunks" => chunks(args),
+        "convert" => convert(args),
+        "validate" => validate(args),
+        "meta" => match args.positional(1, "meta command, get or set")? {
+            "get" => meta_get(args),
+            "set" => meta_set(args),
+            other => Err(format!("unknown meta command '{}'", other)),
+        },
+        "help" => {
+            print!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        other => Err(format!("unknown command '{}', see wavtool help", other)),
+    }
+}
+
+fn read(path: &str) -> Result<AnyWav, String> {
+    AnyWav::read(path).map_err(|err| format!("{}: {}", path, err))
+}
 
-    //     reader.read_exact(buffer)?;
-    //     Ok(data)
-    // }
-    // }
-    pub fn read_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        let mut data = Data::default();
+fn info(args: &Args) -> Result<ExitCode, String> {
+    args.check(2, &[])?;
+    let path = args.positional(1, "file")?;
+    let wav = read(path)?;
 
-        let mut buffer = [0; 4];
-        buffer.copy_from_slice(&bytes[0..4]);
-        data.id = i32::from_le_bytes(buffer);
+    with_wav!(&wav, wav => {
+        let format = wav.format();
+        let frames = wav.len_frames();
+        let container = match wav.container() {
+            Container::Wave => format!("{:?}", wav.form()),
+            container => format!("{:?}", container),
+        };
 
-        buffer.copy_from_slice(&bytes[8..12]);
-        data.size = i32::from_le_bytes(buffer);
+        println!("container:   {}", container);
+        println!("encoding:    {}", describe(format));
+        println!("channels:    {}", format.get_channels());
+        if let Some(mask) = format.get_channel_mask() {
+            println!("channel mask: {:?}", mask);
+        }
+        println!("sample rate: {} Hz", format.get_sample_rate());
+        println!(
+            "duration:    {} frames, {:.3} s",
+            frames,
+            frames as f64 / format.get_sample_rate().max(1) as f64
+        );
+        for chunk in wav.chunks() {
+            println!("chunk:       {} ({} bytes)", chunk.id(), chunk.data().len());
+        }
+        if let Some(info) = wav.info() {
+            for (id, text) in info.entries() {
+                println!("tag:         {} = {}", tag_name(*id), text);
+            }
+        }
+    });
 
-        Ok(data)
+    Ok(ExitCode::SUCCESS)
+}
+
+fn describe(format: &HeaderFormat) -> String {
+    let bits = format.get_bits_per_sample();
+    match format.get_valid_bits_per_sample() {
+        valid if valid != bits && valid != 0 => {
+            format!(
+                "{:?}, {} bits ({} valid)",
+                format.get_encoding(),
+                bits,
+                valid
+            )
+        }
+        _ => format!("{:?}, {} bits", format.get_encoding(), bits),
     }
-    pub fn load<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        let mut id = [0; 4];
-        reader.read_exact(&mut id)?;
+}
 
-        let mut size = [0; 4];
-        reader.read_exact(&mut size)?;
+// Walks the chunks of the file as stored, so unread and broken ones show up too
+fn chunks(args: &Args) -> Result<ExitCode, String> {
+    args.check(2, &["bytes"])?;
+    let path = args.positional(1, "file")?;
+    let dump = match args.option("bytes") {
+        Some(bytes) => bytes
+            .parse()
+            .map_err(|_| format!("--bytes takes a number, not '{}'", bytes))?,
+        None => DUMP_BYTES,
+    };
+    let bytes = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
 
-        Ok(Self {
-            id: i32::from_le_bytes(id),
-            size: i32::from_le_bytes(size),
+    let Some(magic) = bytes.get(..4) else {
+        return Err(format!("{}: too short to hold chunks", path));
+    };
+    let byte_order = match magic {
+        b"RIFF" | b"RF64" | b"BW64" => ByteOrder::LittleEndian,
+        b"RIFX" | b"FORM" => ByteOrder::BigEndian,
+        _ => {
+            return Err(format!(
+                "{}: chunks are listed for RIFF, RIFX and AIFF files",
+                path
+            ))
+        }
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let field: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(match byte_order {
+            ByteOrder::LittleEndian => u32::from_le_bytes(field),
+            ByteOrder::BigEndian => u32::from_be_bytes(field),
         })
+    };
+    // RF64 data size, from the ds64 chunk that comes first
+    let ds64_data_size = (bytes.get(12..16) == Some(b"ds64"))
+        .then(|| bytes.get(28..36))
+        .flatten()
+        .map(|field| u64::from_le_bytes(field.try_into().expect("8 bytes")));
+
+    println!(
+        "{:08x}  {}  {} bytes, {}",
+        0,
+        String::from_utf8_lossy(magic),
+        u32_at(4).unwrap_or_default(),
+        String::from_utf8_lossy(bytes.get(8..12).unwrap_or_default())
+    );
+
+    let mut position = 12;
+    // Sizes come from the file and may be anything, positions saturate past its end
+    while bytes.len().saturating_sub(position) >= 8 {
+        let id = &bytes[position..position + 4];
+        let size = match (u32_at(position + 4).expect("8 bytes"), ds64_data_size) {
+            (u32::MAX, Some(size)) if id == b"data" => size,
+            (size, _) => size as u64,
+        };
+        let size_in_memory = usize::try_from(size).unwrap_or(usize::MAX);
+        let body_start = position + 8;
+        let body_end = body_start.saturating_add(size_in_memory).min(bytes.len());
+
+        let stored = body_end - body_start;
+        if (stored as u64) < size {
+            println!(
+                "{:08x}  {}  {} bytes, {} in the file",
+                position,
+                String::from_utf8_lossy(id),
+                size,
+                stored
+            );
+        } else {
+            println!(
+                "{:08x}  {}  {} bytes",
+                position,
+                String::from_utf8_lossy(id),
+                size
+            );
+        }
+        let shown = &bytes[body_start..body_end.min(body_start.saturating_add(dump))];
+        for (line, row) in shown.chunks(16).enumerate() {
+            println!("          {}", hex_row(body_start + line * 16, row));
+        }
+
+        position = body_start
+            .saturating_add(size_in_memory)
+            .saturating_add(size_in_memory % 2);
+    }
+    if position < bytes.len() {
+        println!("{:08x}  {} stray bytes", position, bytes.len() - position);
     }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// One hexdump line: offset, up to 16 bytes in hex, and as text
+fn hex_row(offset: usize, row: &[u8]) -> String {
+    let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let text: String = row
+        .iter()
+        .map(|&byte| match byte {
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        })
+        .collect();
+
+    format!("{:08x}  {:<47}  |{}|", offset, hex.join(" "), text)
+}
+
+fn convert(args: &Args) -> Result<ExitCode, String> {
+    args.check(3, &["bits", "encoding", "container", "dither"])?;
+    let input = args.positional(1, "input file")?;
+    let output = args.positional(2, "output file")?;
+    let wav = read(input)?;
+
+    let source = with_wav!(&wav, wav => wav.format().clone());
+    let (sample_format, encoding) = target_format(&source, args)?;
+    let dither = match args.option("dither") {
+        None | Some("none") => Dither::None,
+        Some("triangular") => Dither::Triangular,
+        Some(other) => return Err(format!("unknown dither '{}'", other)),
+    };
+    let (container, form) = match args.option("container") {
+        Some(name) => parse_container(name)?,
+        None => container_for(output)
+            .unwrap_or((with_wav!(&wav, wav => wav.container()), RiffForm::Riff)),
+    };
+
+    let mut converted = wav
+        .convert(sample_format, encoding, dither)
+        .map_err(|err| format!("{}: {}", output, err))?;
+    with_wav!(&mut converted, wav => {
+        wav.set_container(container);
+        if container == Container::Wave {
+            wav.set_form(form);
+        }
+        wav.write_to_file(output)
+            .map_err(|err| format!("{}: {}", output, err))?;
+        println!("{}: {}, {:?}", output, describe(wav.format()), container);
+    });
+
+    Ok(ExitCode::SUCCESS)
 }
 
-const FILE_PATH: &str = "test.dat";
+// Sample format and encoding asked for, what isn't given is kept from the source
+fn target_format(source: &HeaderFormat, args: &Args) -> Result<(SampleFormat, Encoding), String> {
+    let encoding = match args.option("encoding") {
+        None => source.get_encoding(),
+        Some("pcm") => Encoding::LPCM,
+        Some("float") => Encoding::IEEE,
+        Some("alaw") => Encoding::ALAW,
+        Some("mulaw") => Encoding::MULAW,
+        Some(other) => return Err(format!("unknown encoding '{}'", other)),
+    };
+    let bits = match args.option("bits") {
+        Some(bits) => Some(
+            bits.parse::<u16>()
+                .map_err(|_| format!("--bits takes a number, not '{}'", bits))?,
+        ),
+        // Companded sources are 8 bits on disk but 16 once expanded
+        None if matches!(source.get_encoding(), Encoding::ALAW | Encoding::MULAW) => None,
+        None if source.get_encoding() == encoding => Some(source.get_bits_per_sample()),
+        None => None,
+    };
 
-fn main() {
-    println!("Hello, world!");
-    let mut file = fs::File::open(FILE_PATH).expect("file");
+    let sample_format = match (encoding, bits) {
+        (Encoding::LPCM, Some(8)) => SampleFormat::U8,
+        (Encoding::LPCM, Some(16) | None) => SampleFormat::I16,
+        (Encoding::LPCM, Some(24)) => SampleFormat::I24,
+        (Encoding::LPCM, Some(32)) => SampleFormat::I32,
+        (Encoding::IEEE, Some(32) | None) => SampleFormat::F32,
+        (Encoding::IEEE, Some(64)) => SampleFormat::F64,
+        (Encoding::ALAW | Encoding::MULAW, Some(8) | None) => SampleFormat::I16,
+        (encoding, Some(bits)) => {
+            return Err(format!("{:?} can't be {} bits", encoding, bits));
+        }
+    };
 
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf).expect("read");
+    Ok((sample_format, encoding))
+}
 
-    let data = Data::read_bytes(buf.as_slice());
+fn parse_container(name: &str) -> Result<(Container, RiffForm), String> {
+    Ok(match name {
+        "wav" => (Container::Wave, RiffForm::Riff),
+        "rf64" => (Container::Wave, RiffForm::Rf64),
+        "bw64" => (Container::Wave, RiffForm::Bw64),
+        "rifx" => (Container::Rifx, RiffForm::Riff),
+        "w64" => (Container::Wave64, RiffForm::Riff),
+        "aiff" => (Container::Aiff, RiffForm::Riff),
+        "aifc" => (Container::AiffC(ByteOrder::BigEndian), RiffForm::Riff),
+        "au" => (Container::Au, RiffForm::Riff),
+        other => return Err(format!("unknown container '{}'", other)),
+    })
+}
 
-    // let contents = fs::read(FILE_PATH).expect("Should have been able to read the file");
-    println!("With text:\n{data:?}");
+fn container_for(path: &str) -> Option<(Container, RiffForm)> {
+    let (_, extension) = path.rsplit_once('.')?;
+    let name = match extension.to_ascii_lowercase().as_str() {
+        "wav" | "wave" | "bwf" => "wav",
+        "rf64" => "rf64",
+        "w64" => "w64",
+        "aif" | "aiff" => "aiff",
+        "aifc" => "aifc",
+        "au" | "snd" => "au",
+        _ => return None,
+    };
+
+    parse_container(name).ok()
+}
+
+fn validate(args: &Args) -> Result<ExitCode, String> {
+    args.check(2, &[])?;
+    let path = args.positional(1, "file")?;
+    let file = fs::File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+    let diagnostics = lint(file).map_err(|err| format!("{}: {}", path, err))?;
+
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    println!(
+        "{}: {} errors, {} warnings",
+        path,
+        errors,
+        diagnostics.len() - errors
+    );
+
+    Ok(if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn meta_get(args: &Args) -> Result<ExitCode, String> {
+    args.check(4, &[])?;
+    let path = args.positional(2, "file")?;
+    let info = with_wav!(&read(path)?, wav => wav.info()).unwrap_or_default();
+
+    match args.positional(3, "tag") {
+        Ok(tag) => match info.get(parse_tag(tag)?) {
+            Some(text) => println!("{}", text),
+            None => return Ok(ExitCode::FAILURE),
+        },
+        Err(_) => {
+            for (id, text) in info.entries() {
+                println!("{} = {}", tag_name(*id), text);
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn meta_set(args: &Args) -> Result<ExitCode, String> {
+    args.check(5, &["out"])?;
+    let path = args.positional(2, "file")?;
+    let tag = parse_tag(args.positional(3, "tag")?)?;
+    let text = args.positional(4, "text")?;
+    let output = args.option("out").unwrap_or(path);
+    let mut wav = read(path)?;
+
+    with_wav!(&mut wav, wav => {
+        if text.is_empty() {
+            wav.remove_info_text(tag)
+        } else {
+            wav.set_info_text(tag, text)
+        }
+        .map_err(|err| format!("{}: {}", path, err))?;
+        wav.write_to_file(output)
+            .map_err(|err| format!("{}: {}", output, err))?;
+    });
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_tag(tag: &str) -> Result<FourCC, String> {
+    match TAGS.iter().find(|(name, _)| name.eq_ignore_ascii_case(tag)) {
+        Some((_, id)) => Ok(*id),
+        None => FourCC::from(tag).map_err(|_| format!("unknown tag '{}'", tag)),
+    }
+}
+
+fn tag_name(id: FourCC) -> String {
+    match TAGS.iter().find(|(_, tag)| *tag == id) {
+        Some((name, _)) => name.to_string(),
+        None => id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Args {
+        Args::parse(line.split(' ').map(str::to_string)).unwrap()
+    }
+
+    #[test]
+    fn picks_target_format() {
+        let source = HeaderFormat::new(Encoding::LPCM, 2, 44_100, 3);
+
+        assert_eq!(
+            target_format(&source, &args("convert a b")),
+            Ok((SampleFormat::I24, Encoding::LPCM))
+        );
+        assert_eq!(
+            target_format(&source, &args("convert a b --encoding float")),
+            Ok((SampleFormat::F32, Encoding::IEEE))
+        );
+        assert_eq!(
+            target_format(&source, &args("convert a b --encoding mulaw")),
+            Ok((SampleFormat::I16, Encoding::MULAW))
+        );
+        assert!(target_format(&source, &args("convert a b --bits 12")).is_err());
+    }
+
+    #[test]
+    fn names_tags_and_containers() {
+        assert_eq!(parse_tag("Title"), Ok(ListInfo::TITLE));
+        assert_eq!(parse_tag("IMED"), Ok(FourCC::new(*b"IMED")));
+        assert!(parse_tag("media").is_err());
+        assert_eq!(tag_name(ListInfo::SOFTWARE), "software");
+
+        assert_eq!(
+            container_for("take.AIF"),
+            Some((Container::Aiff, RiffForm::Riff))
+        );
+        assert_eq!(container_for("take"), None);
+        assert_eq!(
+            hex_row(16, b"fmt \x10"),
+            format!("{:08x}  {:<47}  |fmt .|", 16, "66 6d 74 20 10")
+        );
+    }
 }